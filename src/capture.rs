@@ -0,0 +1,116 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::Read;
+use std::os::fd::OwnedFd;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use nix::fcntl::fcntl;
+use nix::fcntl::FcntlArg;
+use nix::fcntl::OFlag;
+
+use crate::rotate::RotatingFile;
+
+/// Maximum bytes kept by [`Ring`].
+pub const RING_SIZE: usize = 64 * 1024;
+
+/// The output kept in memory, the oldest bytes are dropped when it is full.
+#[derive(Debug, Clone, Default)]
+pub struct Ring(Arc<Mutex<RingBuf>>);
+
+#[derive(Debug, Default)]
+struct RingBuf {
+    buf: VecDeque<u8>,
+
+    /// Bytes written since created
+    total: u64,
+}
+
+impl Ring {
+    pub fn write(&self, data: &[u8]) {
+        let mut ring = self.0.lock().unwrap();
+        let len = ring.buf.len();
+        let over = (len + data.len()).saturating_sub(RING_SIZE);
+
+        ring.buf.drain(..over.min(len));
+        ring.buf
+            .extend(&data[data.len().saturating_sub(RING_SIZE)..]);
+        ring.total += data.len() as u64;
+    }
+
+    /// Return the bytes kept after `offset`, and the offset of the end.
+    pub fn since(&self, offset: u64) -> (Vec<u8>, u64) {
+        let ring = self.0.lock().unwrap();
+        let start = ring.total - ring.buf.len() as u64;
+        let skip = offset.saturating_sub(start) as usize;
+
+        (ring.buf.iter().skip(skip).copied().collect(), ring.total)
+    }
+}
+
+/// The stdout and stderr of process kept in memory if the log path is not set.
+#[derive(Debug, Clone, Default)]
+pub struct Capture {
+    pub out: Ring,
+
+    pub err: Ring,
+}
+
+impl Capture {
+    /// Both stdout and stderr are written to one ring, like the log of kcptun.
+    pub fn shared() -> Self {
+        let ring = Ring::default();
+
+        Self {
+            out: ring.clone(),
+            err: ring,
+        }
+    }
+}
+
+/// Where the output of pipe is copied to.
+#[derive(Debug, Clone)]
+pub enum Sink {
+    File(Arc<Mutex<RotatingFile>>),
+
+    Ring(Ring),
+}
+
+impl Sink {
+    fn write(&self, data: &[u8]) {
+        match self {
+            Sink::File(file) => {
+                let mut file = file.lock().unwrap();
+
+                if let Err(e) = file.write_all(data) {
+                    eprintln!("WARN! Failed write log `{}`: {e:?}", file.path.display());
+                }
+            }
+            Sink::Ring(ring) => ring.write(data),
+        }
+    }
+}
+
+/// Copy the output of the pipe to the sink in a thread, until the pipe is closed.
+pub fn pump(pipe: OwnedFd, sink: Sink) -> std::io::Result<()> {
+    // the pipe of tokio is non-blocking
+    fcntl(&pipe, FcntlArg::F_SETFL(OFlag::empty()))?;
+
+    let mut pipe = File::from(pipe);
+
+    std::thread::Builder::new()
+        .name(String::from("log-pump"))
+        .spawn(move || {
+            let mut buf = [0; 8192];
+
+            loop {
+                match pipe.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(len) => sink.write(&buf[..len]),
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                    Err(_) => break,
+                }
+            }
+        })?;
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use std::ffi::OsString;
 use std::fmt::Display;
+use std::path::Path;
 use std::path::PathBuf;
 
 use cote::prelude::error;
@@ -7,16 +8,19 @@ use cote::prelude::CoteOpt;
 use cote::prelude::CoteVal;
 use cote::shell::value::repeat_values;
 use cote::shell::value::Values;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::inherit::Inherit;
+
 #[derive(Clone, Serialize, Deserialize, Debug, Default)]
 pub struct SsConfig {
     pub server: String,
 
     pub server_port: u32,
 
-    pub password: String,
+    pub password: Secret,
 
     pub timeout: u32,
 
@@ -25,13 +29,18 @@ pub struct SsConfig {
     pub fast_open: bool,
 }
 
+/// The default listen port of kcptun `{port + 1}`, `None` if it is not a valid port.
+pub fn default_kcp_port(port: u32) -> Option<u32> {
+    port.checked_add(1).filter(|v| *v <= u16::MAX as u32)
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, Default)]
 pub struct KcpConfig {
     pub server: String,
 
     pub crypt: Crypt,
 
-    pub key: String,
+    pub key: Secret,
 
     pub send_wnd: u32,
 
@@ -50,8 +59,82 @@ pub struct KcpConfig {
     pub comp: bool,
 }
 
+impl KcpConfig {
+    /// The default settings of kcptun.
+    pub fn kcptun_default() -> Self {
+        Self {
+            server: String::from("127.0.0.1"),
+            crypt: Crypt::Aes,
+            key: Secret::from("it's a secrect"),
+            send_wnd: 1024,
+            recv_wnd: 1024,
+            mtu: 1350,
+            mode: KcpMode::Fast,
+            dscp: 0,
+            data_shard: 10,
+            parity_shard: 3,
+            comp: true,
+        }
+    }
+
+    /// Arguments of kcptun except the addresses, same for both server and client.
+    ///
+    /// The key must be resolved by [`Secret::resolve`] before.
+    pub fn tuning_args(&self) -> Vec<String> {
+        let mut args = vec![
+            String::from("-crypt"),
+            self.crypt.to_string(),
+            String::from("-key"),
+            self.key.as_plain().unwrap_or_default().to_string(),
+            String::from("-sndwnd"),
+            self.send_wnd.to_string(),
+            String::from("-rcvwnd"),
+            self.recv_wnd.to_string(),
+            String::from("-dscp"),
+            self.dscp.to_string(),
+            String::from("-datashard"),
+            self.data_shard.to_string(),
+            String::from("-parityshard"),
+            self.parity_shard.to_string(),
+            String::from("-mode"),
+            self.mode.to_string(),
+            String::from("-mtu"),
+            self.mtu.to_string(),
+        ];
+
+        if !self.comp {
+            args.push(String::from("-nocomp"));
+        }
+        args
+    }
+
+    /// Configuration of kcptun server given by `-c`, which keeps the key out of the command line.
+    ///
+    /// The key must be resolved by [`Secret::resolve`] before.
+    pub fn server_config(&self, listen: &str, target: &str) -> serde_json::Value {
+        serde_json::json!({
+            "listen": listen,
+            "target": target,
+            "key": self.key.as_plain().unwrap_or_default(),
+            "crypt": self.crypt.to_string(),
+            "mode": self.mode.to_string(),
+            "mtu": self.mtu,
+            "sndwnd": self.send_wnd,
+            "rcvwnd": self.recv_wnd,
+            "datashard": self.data_shard,
+            "parityshard": self.parity_shard,
+            "dscp": self.dscp,
+            "nocomp": !self.comp,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct DeployConfig {
+    /// Name of the profile extended by the configuration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+
     pub bin: PathBuf,
 
     pub kcp: PathBuf,
@@ -65,10 +148,447 @@ pub struct DeployConfig {
     pub ss_cfg: SsConfig,
 
     pub kcp_cfg: Option<KcpConfig>,
+
+    #[serde(default)]
+    pub restart: RestartConfig,
+
+    #[serde(default)]
+    pub log: LogConfig,
+}
+
+/// The password or key, can be a reference resolved when start.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Secret {
+    Plain(String),
+
+    Ref(SecretRef),
+}
+
+/// Reference of the secret, such as `{"env": "SS_PASS_0"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretRef {
+    /// Read the environment variable
+    Env(String),
+
+    /// Read the file, the trailing newline is removed
+    File(PathBuf),
+
+    /// Run the command by `sh -c`, use the stdout without trailing newline
+    Cmd(String),
+}
+
+impl Default for Secret {
+    fn default() -> Self {
+        Self::Plain(String::default())
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(val: &str) -> Self {
+        Self::Plain(val.to_string())
+    }
+}
+
+impl From<String> for Secret {
+    fn from(val: String) -> Self {
+        Self::Plain(val)
+    }
+}
+
+impl Secret {
+    /// The plain value, or `None` if it is a reference.
+    pub fn as_plain(&self) -> Option<&str> {
+        match self {
+            Self::Plain(val) => Some(val),
+            Self::Ref(_) => None,
+        }
+    }
+
+    /// The references are kept, they are not secrets.
+    pub fn redacted(&self) -> Self {
+        match self {
+            Self::Plain(_) => Self::from(REDACTED),
+            Self::Ref(_) => self.clone(),
+        }
+    }
+
+    /// Return the plain value or the value of reference.
+    pub async fn resolve(&self) -> color_eyre::Result<String> {
+        let trim = |val: String| val.trim_end_matches(['\r', '\n']).to_string();
+
+        match self {
+            Self::Plain(val) => Ok(val.clone()),
+            Self::Ref(SecretRef::Env(name)) => std::env::var(name).map_err(|e| {
+                color_eyre::Report::msg(format!("Can not read environment variable `{name}`: {e}"))
+            }),
+            Self::Ref(SecretRef::File(path)) => {
+                let path = shellexpand::path::full(path)?;
+
+                Ok(trim(tokio::fs::read_to_string(&path).await.map_err(
+                    |e| {
+                        color_eyre::Report::msg(format!(
+                            "Can not read secret file `{}`: {e}",
+                            path.display()
+                        ))
+                    },
+                )?))
+            }
+            Self::Ref(SecretRef::Cmd(cmd)) => {
+                let output = tokio::process::Command::new("sh")
+                    .arg("-c")
+                    .arg(cmd)
+                    .stdin(std::process::Stdio::null())
+                    .stderr(std::process::Stdio::inherit())
+                    .output()
+                    .await?;
+
+                if !output.status.success() {
+                    return Err(color_eyre::Report::msg(format!(
+                        "Secret command `{cmd}` failed: {}",
+                        output.status
+                    )));
+                }
+                Ok(trim(String::from_utf8(output.stdout)?))
+            }
+        }
+    }
+}
+
+/// Extensions of the configuration files.
+pub const EXTENSIONS: &[&str] = &["json", "toml", "yaml", "yml"];
+
+/// Format of the configuration files, decided by the extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Json,
+
+    Toml,
+
+    Yaml,
+}
+
+impl FileFormat {
+    /// The files without known extension are JSON.
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension().and_then(|v| v.to_str()) {
+            Some("toml") => Self::Toml,
+            Some("yaml" | "yml") => Self::Yaml,
+            _ => Self::Json,
+        }
+    }
+
+    pub fn parse<T: DeserializeOwned>(&self, content: &str) -> color_eyre::Result<T> {
+        Ok(match self {
+            Self::Json => serde_json::from_str(content)?,
+            Self::Toml => toml::from_str(content)?,
+            Self::Yaml => serde_yaml::from_str(content)?,
+        })
+    }
+
+    pub fn to_string<T: Serialize>(&self, value: &T) -> color_eyre::Result<String> {
+        Ok(match self {
+            Self::Json => serde_json::to_string_pretty(value)?,
+            Self::Toml => toml::to_string_pretty(value)?,
+            Self::Yaml => serde_yaml::to_string(value)?,
+        })
+    }
+}
+
+/// The configuration file in object form, the entries extend the defaults and profiles.
+///
+/// It is required by TOML, which top level must be a table.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ConfigFile {
+    #[serde(flatten)]
+    pub inherit: Inherit,
+
+    pub configs: Vec<serde_json::Value>,
+}
+
+impl ConfigFile {
+    /// Accept both the object form and the bare array of entries.
+    pub fn from_value(value: serde_json::Value) -> color_eyre::Result<Self> {
+        Ok(match value {
+            serde_json::Value::Array(configs) => Self {
+                configs,
+                ..Default::default()
+            },
+            value => serde_json::from_value(value)?,
+        })
+    }
+
+    /// Resolve the entries with the defaults and profiles.
+    pub fn resolve(&self) -> color_eyre::Result<Vec<DeployConfig>> {
+        self.configs
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                self.inherit.resolve(entry).map_err(|e| {
+                    color_eyre::Report::msg(format!("Invalid configuration at index {index}: {e}"))
+                })
+            })
+            .collect()
+    }
+}
+
+/// Placeholder of the secrets in output.
+pub const REDACTED: &str = "******";
+
+impl DeployConfig {
+    /// Return a copy with the plain password and key replaced by [`REDACTED`].
+    pub fn redacted(&self) -> Self {
+        let mut cfg = self.clone();
+
+        cfg.ss_cfg.password = cfg.ss_cfg.password.redacted();
+        if let Some(kcp_cfg) = cfg.kcp_cfg.as_mut() {
+            kcp_cfg.key = kcp_cfg.key.redacted();
+        }
+        cfg
+    }
+
+    /// Set the field at `path` such as `kcp_cfg.mode`, see [`FIELDS`] for the paths.
+    ///
+    /// The optional paths accept `none` to unset them,
+    /// and the fields of `kcp_cfg` start from [`KcpConfig::kcptun_default`] if it is not set.
+    pub fn set(&mut self, path: &str, val: &str) -> Result<(), cote::Error> {
+        let optional = |val: &str| (!matches!(val, "" | "none")).then(|| PathBuf::from(val));
+
+        match path {
+            "bin" => self.bin = PathBuf::from(val),
+            "kcp" => self.kcp = PathBuf::from(val),
+            "err_log" => self.err_log = optional(val),
+            "out_log" => self.out_log = optional(val),
+            "kcp_log" => self.kcp_log = optional(val),
+            "ss_cfg.server" => self.ss_cfg.server = val.to_string(),
+            "ss_cfg.server_port" => self.ss_cfg.server_port = parse(path, val)?,
+            "ss_cfg.password" => self.ss_cfg.password = Secret::from(val),
+            "ss_cfg.timeout" => self.ss_cfg.timeout = parse(path, val)?,
+            "ss_cfg.method" => self.ss_cfg.method = Method::try_from(val)?,
+            "ss_cfg.fast_open" => self.ss_cfg.fast_open = parse(path, val)?,
+            "kcp_cfg" => match val {
+                "none" => self.kcp_cfg = None,
+                "default" => self.kcp_cfg = Some(KcpConfig::kcptun_default()),
+                _ => return Err(error!("Value of `kcp_cfg` must be `none` or `default`")),
+            },
+            "restart.policy" => self.restart.policy = RestartPolicy::try_from(val)?,
+            "restart.max_retries" => self.restart.max_retries = parse(path, val)?,
+            "restart.backoff" => self.restart.backoff = parse(path, val)?,
+            "restart.max_backoff" => self.restart.max_backoff = parse(path, val)?,
+            "log.mode" => self.log.mode = LogMode::try_from(val)?,
+            "log.max_size" => {
+                self.log.max_size = match val {
+                    "" | "none" => None,
+                    _ => Some(parse(path, val)?),
+                }
+            }
+            "log.daily" => self.log.daily = parse(path, val)?,
+            "log.keep" => self.log.keep = parse(path, val)?,
+            "log.compress" => self.log.compress = parse(path, val)?,
+            _ => {
+                let name = path
+                    .strip_prefix("kcp_cfg.")
+                    .filter(|_| FIELDS.contains(&path))
+                    .ok_or_else(|| error!("Unknown field `{}`", path))?;
+                let cfg = self.kcp_cfg.get_or_insert_with(KcpConfig::kcptun_default);
+
+                match name {
+                    "server" => cfg.server = val.to_string(),
+                    "crypt" => cfg.crypt = Crypt::try_from(val)?,
+                    "key" => cfg.key = Secret::from(val),
+                    "send_wnd" => cfg.send_wnd = parse(path, val)?,
+                    "recv_wnd" => cfg.recv_wnd = parse(path, val)?,
+                    "mtu" => cfg.mtu = parse(path, val)?,
+                    "mode" => cfg.mode = KcpMode::try_from(val)?,
+                    "dscp" => cfg.dscp = parse(path, val)?,
+                    "data_shard" => cfg.data_shard = parse(path, val)?,
+                    "parity_shard" => cfg.parity_shard = parse(path, val)?,
+                    "comp" => cfg.comp = parse(path, val)?,
+                    _ => unreachable!("the path is checked by FIELDS"),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Paths of the fields can be changed by [`DeployConfig::set`].
+pub const FIELDS: &[&str] = &[
+    "bin",
+    "kcp",
+    "err_log",
+    "out_log",
+    "kcp_log",
+    "ss_cfg.server",
+    "ss_cfg.server_port",
+    "ss_cfg.password",
+    "ss_cfg.timeout",
+    "ss_cfg.method",
+    "ss_cfg.fast_open",
+    "kcp_cfg",
+    "kcp_cfg.server",
+    "kcp_cfg.crypt",
+    "kcp_cfg.key",
+    "kcp_cfg.send_wnd",
+    "kcp_cfg.recv_wnd",
+    "kcp_cfg.mtu",
+    "kcp_cfg.mode",
+    "kcp_cfg.dscp",
+    "kcp_cfg.data_shard",
+    "kcp_cfg.parity_shard",
+    "kcp_cfg.comp",
+    "restart.policy",
+    "restart.max_retries",
+    "restart.backoff",
+    "restart.max_backoff",
+    "log.mode",
+    "log.max_size",
+    "log.daily",
+    "log.keep",
+    "log.compress",
+];
+
+fn parse<T: std::str::FromStr>(path: &str, val: &str) -> Result<T, cote::Error>
+where
+    T::Err: Display,
+{
+    val.parse()
+        .map_err(|e| error!("Invalid value `{}` of `{}`: {}", val, path, e))
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct RestartConfig {
+    pub policy: RestartPolicy,
+
+    /// Maximum number of consecutive restarts
+    pub max_retries: u32,
+
+    /// Initial backoff in milliseconds, doubled after every failed restart
+    pub backoff: u64,
+
+    /// Upper bound of the backoff in milliseconds
+    pub max_backoff: u64,
+}
+
+impl Default for RestartConfig {
+    fn default() -> Self {
+        Self {
+            policy: RestartPolicy::default(),
+            max_retries: 5,
+            backoff: 1000,
+            max_backoff: 60000,
+        }
+    }
+}
+
+impl RestartConfig {
+    /// Return the delay before the `attempt`-th restart.
+    pub fn delay(&self, attempt: u32) -> std::time::Duration {
+        let backoff = self
+            .backoff
+            .saturating_mul(1u64.checked_shl(attempt).unwrap_or(u64::MAX));
+
+        std::time::Duration::from_millis(backoff.min(self.max_backoff))
+    }
+}
+
+/// How the log files of ssserver and kcptun are written.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct LogConfig {
+    pub mode: LogMode,
+
+    /// Rotate the file when it is larger than the size in bytes
+    pub max_size: Option<u64>,
+
+    /// Rotate the file when the date (UTC) changes
+    pub daily: bool,
+
+    /// Maximum number of the rotated files kept
+    pub keep: u32,
+
+    /// Compress the rotated files by gzip
+    pub compress: bool,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            mode: LogMode::default(),
+            max_size: None,
+            daily: false,
+            keep: 7,
+            compress: false,
+        }
+    }
+}
+
+impl LogConfig {
+    /// The output is written by rssdeploy through pipes if the rotation is enabled.
+    pub fn rotating(&self) -> bool {
+        self.max_size.is_some() || self.daily
+    }
 }
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Serialize, Deserialize, CoteVal, CoteOpt)]
-#[serde(try_from = "&str")]
+#[serde(try_from = "String")]
+#[coteval(mapstr = TryFrom::try_from)]
+pub enum LogMode {
+    /// Keep the output of previous run
+    #[default]
+    Append,
+
+    /// Truncate the log files when start
+    Truncate,
+}
+
+impl<'a> TryFrom<&'a str> for LogMode {
+    type Error = cote::Error;
+
+    fn try_from(val: &'a str) -> Result<Self, Self::Error> {
+        match val {
+            "append" | "Append" => Ok(Self::Append),
+            "truncate" | "Truncate" => Ok(Self::Truncate),
+            _ => Err(error!("Unknown log mode: {}", val)),
+        }
+    }
+}
+
+impl TryFrom<String> for LogMode {
+    type Error = cote::Error;
+
+    fn try_from(val: String) -> Result<Self, Self::Error> {
+        Self::try_from(val.as_str())
+    }
+}
+
+impl Display for LogMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                LogMode::Append => "append",
+                LogMode::Truncate => "truncate",
+            }
+        )
+    }
+}
+
+impl LogMode {
+    pub fn values<O>() -> impl Values<O, Err = cote::Error> {
+        repeat_values(|_| {
+            Ok([Self::Append, Self::Truncate]
+                .map(|v| OsString::from(v.to_string()))
+                .to_vec())
+        })
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Serialize, Deserialize, CoteVal, CoteOpt)]
+#[serde(try_from = "String", into = "String")]
 #[coteval(mapstr = TryFrom::try_from)]
 pub enum Method {
     #[default]
@@ -103,11 +623,27 @@ impl<'a> TryFrom<&'a str> for Method {
             "2022-blake3-aes-128-gcm" | "Blake3Aes128_2022" => Ok(Self::Blake3Aes128_2022),
             "2022-blake3-aes-256-gcm" | "Blake3Aes256_2022" => Ok(Self::Blake3Aes256_2022),
             "plain" | "Plain" => Ok(Self::Plain),
+            "none" | "None" => Ok(Self::None),
             _ => Err(error!("Unknown crypt method: {}", val)),
         }
     }
 }
 
+impl TryFrom<String> for Method {
+    type Error = cote::Error;
+
+    fn try_from(val: String) -> Result<Self, Self::Error> {
+        Self::try_from(val.as_str())
+    }
+}
+
+// ssserver only accepts the names of method
+impl From<Method> for String {
+    fn from(val: Method) -> Self {
+        val.to_string()
+    }
+}
+
 impl Display for Method {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -128,6 +664,15 @@ impl Display for Method {
 }
 
 impl Method {
+    /// Length of the key in bytes for the 2022 methods, which password is a base64 key.
+    pub fn key_len(&self) -> Option<usize> {
+        match self {
+            Method::Blake3Aes128_2022 => Some(16),
+            Method::Blake3Aes256_2022 | Method::Blake3ChaCha20Poly1305_2022 => Some(32),
+            _ => None,
+        }
+    }
+
     pub fn values<O>() -> impl Values<O, Err = cote::Error> {
         repeat_values(|_| {
             Ok([
@@ -146,7 +691,66 @@ impl Method {
     }
 }
 
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Serialize, Deserialize, CoteVal, CoteOpt)]
+#[serde(try_from = "String")]
+#[coteval(mapstr = TryFrom::try_from)]
+pub enum RestartPolicy {
+    #[default]
+    Never,
+
+    OnFailure,
+
+    Always,
+}
+
+impl<'a> TryFrom<&'a str> for RestartPolicy {
+    type Error = cote::Error;
+
+    fn try_from(val: &'a str) -> Result<Self, Self::Error> {
+        match val {
+            "never" | "Never" => Ok(Self::Never),
+            "on-failure" | "OnFailure" => Ok(Self::OnFailure),
+            "always" | "Always" => Ok(Self::Always),
+            _ => Err(error!("Unknown restart policy: {}", val)),
+        }
+    }
+}
+
+impl TryFrom<String> for RestartPolicy {
+    type Error = cote::Error;
+
+    fn try_from(val: String) -> Result<Self, Self::Error> {
+        Self::try_from(val.as_str())
+    }
+}
+
+impl Display for RestartPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                RestartPolicy::Never => "never",
+                RestartPolicy::OnFailure => "on-failure",
+                RestartPolicy::Always => "always",
+            }
+        )
+    }
+}
+
+impl RestartPolicy {
+    pub fn values<O>() -> impl Values<O, Err = cote::Error> {
+        repeat_values(|_| {
+            Ok([Self::Never, Self::OnFailure, Self::Always]
+                .map(|v| OsString::from(v.to_string()))
+                .to_vec())
+        })
+    }
+}
+
 #[derive(PartialEq, Eq, Clone, Copy, Serialize, Deserialize, Debug, Default, CoteVal, CoteOpt)]
+#[serde(try_from = "String")]
+#[coteval(mapstr = TryFrom::try_from)]
 pub enum KcpMode {
     Fast3,
 
@@ -160,6 +764,29 @@ pub enum KcpMode {
     Manual,
 }
 
+impl<'a> TryFrom<&'a str> for KcpMode {
+    type Error = cote::Error;
+
+    fn try_from(val: &'a str) -> Result<Self, Self::Error> {
+        match val {
+            "fast3" | "Fast3" => Ok(Self::Fast3),
+            "fast2" | "Fast2" => Ok(Self::Fast2),
+            "fast" | "Fast" => Ok(Self::Fast),
+            "normal" | "Normal" => Ok(Self::Normal),
+            "manual" | "Manual" => Ok(Self::Manual),
+            _ => Err(error!("Unknown kcptun mode: {}", val)),
+        }
+    }
+}
+
+impl TryFrom<String> for KcpMode {
+    type Error = cote::Error;
+
+    fn try_from(val: String) -> Result<Self, Self::Error> {
+        Self::try_from(val.as_str())
+    }
+}
+
 impl Display for KcpMode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -193,6 +820,8 @@ impl KcpMode {
 }
 
 #[derive(PartialEq, Eq, Clone, Copy, Serialize, Deserialize, Debug, Default, CoteVal, CoteOpt)]
+#[serde(try_from = "String", into = "String")]
+#[coteval(mapstr = TryFrom::try_from)]
 pub enum Crypt {
     #[default]
     Aes,
@@ -222,25 +851,63 @@ pub enum Crypt {
     None,
 }
 
+impl<'a> TryFrom<&'a str> for Crypt {
+    type Error = cote::Error;
+
+    fn try_from(val: &'a str) -> Result<Self, Self::Error> {
+        match val {
+            "aes" | "Aes" => Ok(Self::Aes),
+            "aes-128" | "Aes128" => Ok(Self::Aes128),
+            "aes-192" | "Aes192" => Ok(Self::Aes192),
+            "salsa20" | "Salsa20" => Ok(Self::Salsa20),
+            "blowfish" | "BlowFish" => Ok(Self::BlowFish),
+            "twofish" | "TwoFish" => Ok(Self::TwoFish),
+            "cast5" | "Cast5" => Ok(Self::Cast5),
+            "3des" | "3Des" | "Des3" => Ok(Self::Des3),
+            "tea" | "Tea" => Ok(Self::Tea),
+            "xtea" | "XTea" => Ok(Self::XTea),
+            "xor" | "Xor" => Ok(Self::Xor),
+            "sm4" | "Sm4" => Ok(Self::Sm4),
+            "none" | "None" => Ok(Self::None),
+            _ => Err(error!("Unknown kcptun crypt: {}", val)),
+        }
+    }
+}
+
+impl TryFrom<String> for Crypt {
+    type Error = cote::Error;
+
+    fn try_from(val: String) -> Result<Self, Self::Error> {
+        Self::try_from(val.as_str())
+    }
+}
+
+// kcptun only accepts the names of crypt
+impl From<Crypt> for String {
+    fn from(val: Crypt) -> Self {
+        val.to_string()
+    }
+}
+
 impl Display for Crypt {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
             "{}",
             match self {
-                Crypt::Aes => "Aes",
-                Crypt::Aes128 => "Aes128",
-                Crypt::Aes192 => "Aes192",
-                Crypt::Salsa20 => "Salsa20",
-                Crypt::BlowFish => "BlowFish",
-                Crypt::TwoFish => "TwoFish",
-                Crypt::Cast5 => "Cast5",
-                Crypt::Des3 => "3Des",
-                Crypt::Tea => "Tea",
-                Crypt::XTea => "XTea",
-                Crypt::Xor => "Xor",
-                Crypt::Sm4 => "Sm4",
-                Crypt::None => "None",
+                Crypt::Aes => "aes",
+                Crypt::Aes128 => "aes-128",
+                Crypt::Aes192 => "aes-192",
+                Crypt::Salsa20 => "salsa20",
+                Crypt::BlowFish => "blowfish",
+                Crypt::TwoFish => "twofish",
+                Crypt::Cast5 => "cast5",
+                Crypt::Des3 => "3des",
+                Crypt::Tea => "tea",
+                Crypt::XTea => "xtea",
+                Crypt::Xor => "xor",
+                Crypt::Sm4 => "sm4",
+                Crypt::None => "none",
             }
         )
     }
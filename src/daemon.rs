@@ -0,0 +1,335 @@
+use std::io::IsTerminal;
+use std::io::Write;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::path::PathBuf;
+
+use color_eyre::eyre::eyre;
+use nix::sys::stat::umask;
+use nix::sys::stat::Mode;
+use nix::unistd::Uid;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
+use tokio::io::Lines;
+use tokio::net::unix::OwnedReadHalf;
+use tokio::net::unix::OwnedWriteHalf;
+use tokio::net::UnixListener;
+use tokio::net::UnixStream;
+use tokio::select;
+use tokio::signal::unix::signal;
+use tokio::signal::unix::SignalKind;
+use tokio::spawn;
+use tokio::sync::mpsc::channel;
+use tokio::sync::mpsc::unbounded_channel;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::oneshot;
+
+use crate::manager::enter_pressed;
+use crate::manager::AppContext;
+use crate::manager::Manager;
+use crate::manager::Output;
+use crate::manager::Reply;
+use crate::manager::Request;
+use crate::splitted::Splitted;
+use crate::state::create_private_dir;
+use crate::state::runtime_dir;
+
+/// Message send from client to daemon.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum ClientMsg {
+    /// The command line and the working directory of client
+    Line(String, PathBuf),
+
+    Request(Request),
+
+    /// Cancel the running command
+    Cancel,
+}
+
+/// Message send from daemon to client.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum DaemonMsg {
+    Output(String),
+
+    /// The command is finished, with the error if it failed
+    Done(Option<Failure>),
+
+    Reply(Reply),
+}
+
+/// Error of the command run by daemon.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Failure {
+    pub message: String,
+
+    /// The arguments of command are invalid
+    #[serde(default)]
+    pub invalid_args: bool,
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// The socket is in the private runtime directory, others can not bind or connect it.
+pub fn default_socket() -> std::io::Result<PathBuf> {
+    let dir = runtime_dir();
+
+    create_private_dir(&dir)?;
+    Ok(dir.join("rssdeploy.sock"))
+}
+
+#[derive(Debug)]
+struct Job {
+    msg: ClientMsg,
+
+    out: UnboundedSender<String>,
+
+    done: oneshot::Sender<DaemonMsg>,
+
+    cancel: oneshot::Receiver<()>,
+}
+
+/// Own the [`AppContext`] and serve the commands of clients connected to `path`.
+pub async fn serve(path: &Path, mut ctx: AppContext) -> color_eyre::Result<()> {
+    if path.exists() {
+        if UnixStream::connect(path).await.is_ok() {
+            return Err(eyre!("Daemon already listening on `{}`", path.display()));
+        }
+        // remove the stale socket
+        std::fs::remove_file(path)?;
+    }
+
+    // create the socket only accessible by current user, no window for others to connect
+    let old = umask(Mode::from_bits_truncate(0o077));
+    let listener = UnixListener::bind(path);
+
+    umask(old);
+    let listener = listener?;
+    let (job_tx, mut job_rx) = channel::<Job>(32);
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut sighup = signal(SignalKind::hangup())?;
+    let mut sigint = signal(SignalKind::interrupt())?;
+    let mut access_rx = ctx
+        .access
+        .rx
+        .take()
+        .ok_or_else(|| eyre!("The context is served by others"))?;
+
+    println!("Daemon listening on `{}`", path.display());
+    loop {
+        select! {
+            _ = sigterm.recv() => break,
+            // keep the instances running when the terminal is closed
+            _ = sighup.recv() => detach_terminal(),
+            _ = sigint.recv() => break,
+            conn = listener.accept() => {
+                let (stream, _) = conn?;
+
+                spawn(handle_client(stream, job_tx.clone()));
+            }
+            // the follow of commands read the context
+            Some(access) = access_rx.recv() => access(&mut ctx),
+            Some(job) = job_rx.recv() => {
+                let Job { msg, out, done, cancel } = job;
+                let reply = match msg {
+                    ClientMsg::Line(line, cwd) => {
+                        let splitted = Splitted::new(&line);
+                        let args = splitted.split_args(None).args;
+
+                        ctx.out = Output::Channel(out);
+                        ctx.cancel = Some(cancel);
+                        ctx.cwd = Some(cwd);
+                        let ret = Manager::invoke_cmd(args, &mut ctx).await;
+                        let follow = ctx.follow.take();
+
+                        ctx.out = Output::default();
+                        ctx.cancel = None;
+                        ctx.cwd = None;
+                        match (ret, follow) {
+                            (Ok(()), Some(follow)) => {
+                                // keep serving other clients until the follow done
+                                spawn(async move {
+                                    let _ = done.send(done_msg(follow.await));
+                                });
+                                continue;
+                            }
+                            (ret, _) => done_msg(ret),
+                        }
+                    }
+                    ClientMsg::Request(req) => DaemonMsg::Reply(ctx.reply(req)),
+                    // nothing is running
+                    ClientMsg::Cancel => continue,
+                };
+
+                let _ = done.send(reply);
+            }
+        }
+    }
+
+    println!("Daemon exiting, stop all the instances");
+    ctx.shutdown().await?;
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+fn done_msg(ret: color_eyre::Result<()>) -> DaemonMsg {
+    DaemonMsg::Done(ret.err().map(|e| Failure {
+        message: format!("{e:#}"),
+        invalid_args: e.downcast_ref::<cote::Error>().is_some(),
+    }))
+}
+
+/// The output to the closed terminal fails, write them to `/dev/null` instead.
+fn detach_terminal() {
+    if let Ok(null) = std::fs::File::options().write(true).open("/dev/null") {
+        if std::io::stdout().is_terminal() {
+            let _ = nix::unistd::dup2_stdout(&null);
+        }
+        if std::io::stderr().is_terminal() {
+            let _ = nix::unistd::dup2_stderr(&null);
+        }
+    }
+}
+
+async fn handle_client(stream: UnixStream, jobs: Sender<Job>) -> color_eyre::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        let msg: ClientMsg = serde_json::from_str(&line)?;
+        let (out, mut out_rx) = unbounded_channel();
+        let (done, done_rx) = oneshot::channel();
+        let (cancel_tx, cancel) = oneshot::channel();
+        let mut cancel_tx = Some(cancel_tx);
+
+        if matches!(msg, ClientMsg::Cancel) {
+            continue;
+        }
+        jobs.send(Job {
+            msg,
+            out,
+            done,
+            cancel,
+        })
+        .await?;
+        // output is closed before the job done
+        loop {
+            select! {
+                output = out_rx.recv() => match output {
+                    Some(output) => send_msg(&mut writer, &DaemonMsg::Output(output)).await?,
+                    None => break,
+                },
+                // the client may cancel the command while it is running
+                line = lines.next_line() => match line? {
+                    Some(line) => {
+                        if let ClientMsg::Cancel = serde_json::from_str(&line)? {
+                            if let Some(cancel_tx) = cancel_tx.take() {
+                                let _ = cancel_tx.send(());
+                            }
+                        }
+                    }
+                    None => return Ok(()),
+                },
+            }
+        }
+        send_msg(&mut writer, &done_rx.await?).await?;
+    }
+
+    Ok(())
+}
+
+async fn send_msg<T: Serialize>(writer: &mut OwnedWriteHalf, msg: &T) -> color_eyre::Result<()> {
+    let mut msg = serde_json::to_string(msg)?;
+
+    msg.push('\n');
+    writer.write_all(msg.as_bytes()).await?;
+    Ok(())
+}
+
+/// Connection to a running daemon.
+#[derive(Debug)]
+pub struct Client {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+
+    writer: OwnedWriteHalf,
+}
+
+impl Client {
+    pub async fn connect(path: &Path) -> std::io::Result<Self> {
+        // the socket may be bound by others to receive the secrets
+        if std::fs::metadata(path)?.uid() != Uid::current().as_raw() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!("`{}` is not a socket owned by current user", path.display()),
+            ));
+        }
+        let (reader, writer) = UnixStream::connect(path).await?.into_split();
+
+        Ok(Self {
+            lines: BufReader::new(reader).lines(),
+            writer,
+        })
+    }
+
+    async fn recv(&mut self) -> color_eyre::Result<DaemonMsg> {
+        let line = self
+            .lines
+            .next_line()
+            .await?
+            .ok_or_else(|| eyre!("Connection closed by daemon"))?;
+
+        Ok(serde_json::from_str(&line)?)
+    }
+
+    /// Invoke the command on daemon, and write the output to `out`.
+    pub async fn invoke_cmd(
+        &mut self,
+        line: String,
+        out: &mut impl Write,
+    ) -> color_eyre::Result<()> {
+        // the relative paths are resolved against the directory of client
+        let cwd = std::env::current_dir()?;
+
+        send_msg(&mut self.writer, &ClientMsg::Line(line, cwd)).await?;
+        loop {
+            let msg = select! {
+                msg = self.recv() => msg?,
+                _ = tokio::signal::ctrl_c() => {
+                    send_msg(&mut self.writer, &ClientMsg::Cancel).await?;
+                    continue;
+                }
+                _ = enter_pressed() => {
+                    send_msg(&mut self.writer, &ClientMsg::Cancel).await?;
+                    continue;
+                }
+            };
+
+            match msg {
+                DaemonMsg::Output(output) => {
+                    out.write_all(output.as_bytes())?;
+                    out.flush()?;
+                }
+                DaemonMsg::Done(None) => return Ok(()),
+                DaemonMsg::Done(Some(e)) => return Err(e.into()),
+                DaemonMsg::Reply(_) => return Err(eyre!("Unexpected reply from daemon")),
+            }
+        }
+    }
+
+    pub async fn request(&mut self, req: Request) -> color_eyre::Result<Reply> {
+        send_msg(&mut self.writer, &ClientMsg::Request(req)).await?;
+        match self.recv().await? {
+            DaemonMsg::Reply(reply) => Ok(reply),
+            msg => Err(eyre!("Unexpected message from daemon: {msg:?}")),
+        }
+    }
+}
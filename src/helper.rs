@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::env::current_dir;
 use std::ffi::OsString;
 use std::fs::read_dir;
@@ -16,6 +17,7 @@ use cote::shell::value::Values;
 use cote::shell::CompletionManager;
 use cote::shell::Context;
 use rustyline::completion::Completer;
+use rustyline::completion::Pair;
 use rustyline::highlight::MatchingBracketHighlighter;
 use rustyline::validate::MatchingBracketValidator;
 use rustyline::Completer;
@@ -24,6 +26,11 @@ use rustyline::Highlighter;
 use rustyline::Hinter;
 use rustyline::Validator;
 
+use crate::config::Crypt;
+use crate::config::KcpMode;
+use crate::config::LogMode;
+use crate::config::Method;
+use crate::config::RestartPolicy;
 use crate::manager::Manager;
 use crate::manager::Reply;
 use crate::manager::Request;
@@ -61,7 +68,7 @@ pub struct DeployCompleter {
 }
 
 impl Completer for DeployCompleter {
-    type Candidate = String;
+    type Candidate = Pair;
 
     fn complete(
         &self, // FIXME should be `&mut self`
@@ -114,6 +121,9 @@ impl Completer for DeployCompleter {
             let load = parser.take_val::<crate::manager::Load>("load").ok();
             let load_user_config = load.and_then(|v| v.config);
 
+            let import = parser.take_val::<crate::manager::Import>("import").ok();
+            let import_user_configs = import.map(|v| [v.config, v.kcp_config]);
+
             let mut context = Context::new(&args, curr, prev, cword);
             let mut manager = CompletionManager::new(parser);
             let mut shell = DeployShell::new();
@@ -123,53 +133,94 @@ impl Completer for DeployCompleter {
             // set values of load configurations
             if let Ok(load) = manager.find_manager_mut("load") {
                 if let Ok(config_uid) = load.parser().find_uid("--config") {
-                    load.set_values(config_uid, load_json_completion(load_user_config));
+                    load.set_values(config_uid, load_config_completion(load_user_config));
                 }
             }
 
-            // set values of kill id
-            if let Ok(kill) = manager.find_manager_mut("kill") {
-                let proxy = self.proxy.clone();
-                let idlist = std::thread::spawn(move || {
-                    proxy.lock().unwrap().req_sync(Request::FetchInstanceId)
-                })
-                .join()
-                .unwrap();
-
-                if let Ok(Reply::InstanceId(ids)) = idlist {
-                    if let Ok(index_uid) = kill.parser().find_uid("--id") {
-                        kill.set_values(
-                            index_uid,
-                            ids.into_iter()
-                                .map(|v| OsString::from(v.to_string()))
-                                .collect::<Vec<_>>(),
-                        );
+            // set values of import configurations
+            if let Ok(import) = manager.find_manager_mut("import") {
+                let [config, kcp_config] = import_user_configs.unwrap_or_default();
+
+                for (name, val) in [("--config", config), ("--kcp-config", kcp_config)] {
+                    if let Ok(config_uid) = import.parser().find_uid(name) {
+                        import.set_values(config_uid, config_completion(val));
                     }
                 }
             }
 
-            // set values of start index
-            if let Ok(start) = manager.find_manager_mut("start") {
-                let proxy = self.proxy.clone();
-                let indexlist = std::thread::spawn(move || {
-                    proxy.lock().unwrap().req_sync(Request::FetchTaskIndex)
-                })
-                .join()
-                .unwrap();
-
-                if let Ok(Reply::InstanceId(indices)) = indexlist {
-                    if let Ok(index_uid) = start.parser().find_uid("index") {
-                        start.set_values(
-                            index_uid,
-                            indices
-                                .into_iter()
-                                .map(|v| OsString::from(v.to_string()))
-                                .collect::<Vec<_>>(),
-                        );
+            // description of the values
+            let mut descs = HashMap::new();
+
+            // set values of instance id
+            let proxy = self.proxy.clone();
+            let idlist = std::thread::spawn(move || {
+                proxy.lock().unwrap().req_sync(Request::FetchInstanceId)
+            })
+            .join()
+            .unwrap();
+
+            if let Ok(Reply::InstanceId(ids)) = idlist {
+                for name in ["kill", "logs", "status"] {
+                    if let Ok(cmd) = manager.find_manager_mut(name) {
+                        if let Ok(index_uid) = cmd.parser().find_uid("--id") {
+                            cmd.set_values(index_uid, value_with_desc(ids.clone(), &mut descs));
+                        }
                     }
                 }
+            }
+
+            // set values of configuration index
+            let proxy = self.proxy.clone();
+            let indexlist =
+                std::thread::spawn(move || proxy.lock().unwrap().req_sync(Request::FetchTaskIndex))
+                    .join()
+                    .unwrap();
+
+            if let Ok(Reply::TaskIndex(indices)) = indexlist {
+                for name in ["start", "set", "remove", "clone", "export-client"] {
+                    if let Ok(cmd) = manager.find_manager_mut(name) {
+                        if let Ok(index_uid) = cmd.parser().find_uid("index") {
+                            cmd.set_values(index_uid, value_with_desc(indices.clone(), &mut descs));
+                        }
+                    }
+                }
+            }
+            if let Ok(start) = manager.find_manager_mut("start") {
                 if let Ok(config_uid) = start.parser().find_uid("--config") {
-                    start.set_values(config_uid, json_completion(start_user_config));
+                    start.set_values(config_uid, config_completion(start_user_config));
+                }
+            }
+
+            // set values of field path and value
+            if let Ok(set) = manager.find_manager_mut("set") {
+                if let Ok(path_uid) = set.parser().find_uid("path") {
+                    let curr = curr.to_str().unwrap_or_default();
+                    // filter the paths here, the ones are prefix of current word also match
+                    let fields = crate::config::FIELDS.iter().filter(|v| v.starts_with(curr));
+
+                    set.set_values(path_uid, fields.copied().collect::<Vec<_>>());
+                }
+                if let Ok(value_uid) = set.parser().find_uid("value") {
+                    // the path is the second argument of `set`
+                    let path = args
+                        .iter()
+                        .position(|v| v == "set")
+                        .and_then(|v| args.get(v + 2))
+                        .and_then(|v| v.to_str())
+                        .unwrap_or_default();
+
+                    match path {
+                        "ss_cfg.method" => set.set_values(value_uid, Method::values()),
+                        "kcp_cfg.crypt" => set.set_values(value_uid, Crypt::values()),
+                        "kcp_cfg.mode" => set.set_values(value_uid, KcpMode::values()),
+                        "restart.policy" => set.set_values(value_uid, RestartPolicy::values()),
+                        "log.mode" => set.set_values(value_uid, LogMode::values()),
+                        "ss_cfg.fast_open" | "kcp_cfg.comp" | "log.daily" | "log.compress" => {
+                            set.set_values(value_uid, vec!["true", "false"])
+                        }
+                        "kcp_cfg" => set.set_values(value_uid, vec!["default", "none"]),
+                        _ => set.set_values(value_uid, Vec::<&str>::new()),
+                    };
                 }
             }
 
@@ -179,14 +230,53 @@ impl Completer for DeployCompleter {
             }
 
             if manager.complete(&mut shell, &mut context).is_ok() {
-                return Ok(((replace).min(pos), shell.w));
+                let pairs = shell
+                    .w
+                    .into_iter()
+                    .map(|v| Pair {
+                        display: match descs.get(&v) {
+                            Some(desc) => format!("{v} ({desc})"),
+                            None => v.clone(),
+                        },
+                        replacement: v,
+                    })
+                    .collect();
+
+                return Ok(((replace).min(pos), pairs));
             }
         }
 
-        <()>::complete(&(), line, pos, ctx)
+        let (start, candidates) = <()>::complete(&(), line, pos, ctx)?;
+
+        Ok((
+            start,
+            candidates
+                .into_iter()
+                .map(|v| Pair {
+                    display: v.clone(),
+                    replacement: v,
+                })
+                .collect(),
+        ))
     }
 }
 
+/// Collect the descriptions of values, return the values.
+fn value_with_desc(
+    vals: Vec<(usize, String)>,
+    descs: &mut HashMap<String, String>,
+) -> Vec<OsString> {
+    vals.into_iter()
+        .map(|(val, desc)| {
+            let val = val.to_string();
+            let ret = OsString::from(&val);
+
+            descs.insert(val, desc);
+            ret
+        })
+        .collect()
+}
+
 // pub fn split_to_args_without(val: Splitted<'_>, pos: Option<usize>) ->  {
 //     let mut cword = None;
 
@@ -270,20 +360,25 @@ impl<O> Shell<O, Vec<String>> for DeployShell<O> {
     }
 }
 
-pub fn json_completion<O>(val: Option<String>) -> impl Values<O, Err = cote::Error> {
-    file_completion(vec![], val, |v| {
-        v.extension().and_then(|v| v.to_str()) == Some("json")
-    })
+pub fn config_completion<O>(val: Option<String>) -> impl Values<O, Err = cote::Error> {
+    file_completion(vec![], val, is_config)
 }
 
-pub fn load_json_completion<O>(val: Option<String>) -> impl Values<O, Err = cote::Error> {
+pub fn load_config_completion<O>(val: Option<String>) -> impl Values<O, Err = cote::Error> {
     file_completion(
         vec![OsString::from(crate::manager::DEFAULT_CONFIG)],
         val,
-        |v| v.extension().and_then(|v| v.to_str()) == Some("json"),
+        is_config,
     )
 }
 
+/// The configuration files are JSON, TOML or YAML.
+fn is_config(path: &Path) -> bool {
+    path.extension()
+        .and_then(|v| v.to_str())
+        .is_some_and(|v| crate::config::EXTENSIONS.contains(&v))
+}
+
 pub fn file_completion<O>(
     init: Vec<OsString>,
     dir: Option<String>,
@@ -292,7 +387,7 @@ pub fn file_completion<O>(
     repeat_values(move |_| {
         let mut vals = init.clone();
 
-        // search .json in current working directory
+        // search the files in current working directory
         if let Some(paths) = dir.as_ref().and_then(|v| complete_all(v).ok()) {
             vals.extend(paths.into_iter().map(OsString::from));
         } else if let Ok(read_dir) = current_dir().and_then(std::fs::read_dir) {
@@ -0,0 +1,179 @@
+use color_eyre::eyre::eyre;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Map;
+use serde_json::Value;
+
+use crate::config::Crypt;
+use crate::config::DeployConfig;
+use crate::config::KcpMode;
+use crate::config::LogConfig;
+use crate::config::LogMode;
+use crate::config::Method;
+use crate::config::RestartConfig;
+use crate::config::RestartPolicy;
+
+/// The key of entry or profile names the profile it extends.
+pub const EXTENDS: &str = "extends";
+
+/// The `defaults` and named `profiles` extended by the entries of configuration file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Inherit {
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub defaults: Map<String, Value>,
+
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub profiles: Map<String, Value>,
+}
+
+impl Inherit {
+    pub fn is_empty(&self) -> bool {
+        self.defaults.is_empty() && self.profiles.is_empty()
+    }
+
+    /// Merge the `defaults` and the profile chain of `extends`, the parent profile first.
+    pub fn base(&self, extends: Option<&str>) -> color_eyre::Result<Value> {
+        let mut chain = vec![];
+        let mut next = extends;
+
+        while let Some(name) = next {
+            if chain.contains(&name) {
+                return Err(eyre!("Profile `{name}` is extended circularly"));
+            }
+            let profile = self
+                .profiles
+                .get(name)
+                .ok_or_else(|| eyre!("Unknown profile `{name}`"))?;
+
+            chain.push(name);
+            next = profile.get(EXTENDS).and_then(Value::as_str);
+        }
+
+        let mut base = Value::Object(self.defaults.clone());
+
+        for name in chain.into_iter().rev() {
+            merge(&mut base, &self.profiles[name]);
+        }
+        if let Some(base) = base.as_object_mut() {
+            base.remove(EXTENDS);
+        }
+        Ok(base)
+    }
+
+    /// Deep merge the entry into its base and deserialize it.
+    pub fn resolve(&self, entry: &Value) -> color_eyre::Result<DeployConfig> {
+        let mut cfg = self.base(entry.get(EXTENDS).and_then(Value::as_str))?;
+
+        merge(&mut cfg, entry);
+        Ok(serde_json::from_value(cfg)?)
+    }
+
+    /// The reverse of [`resolve`](Self::resolve), keep the fields different from the base.
+    pub fn reduce(&self, cfg: &DeployConfig) -> color_eyre::Result<Value> {
+        // the missing `restart` and `log` are default
+        let mut base = serde_json::json!({
+            "restart": RestartConfig::default(),
+            "log": LogConfig::default(),
+        });
+
+        merge(&mut base, &self.base(cfg.extends.as_deref())?);
+        normalize(&mut base);
+        Ok(diff(&serde_json::to_value(cfg)?, &base).unwrap_or_else(|| Value::Object(Map::new())))
+    }
+}
+
+/// The secrets are replaced as a whole, the references such as `{ env: .. }` can not be merged.
+const REPLACED: [&str; 2] = ["/ss_cfg/password", "/kcp_cfg/key"];
+
+/// Merge the objects recursively, other values of `other` replace the ones of `value`.
+fn merge(value: &mut Value, other: &Value) {
+    merge_at(value, other, "");
+}
+
+fn merge_at(value: &mut Value, other: &Value, pointer: &str) {
+    match (value, other) {
+        (Value::Object(value), Value::Object(other)) if !REPLACED.contains(&pointer) => {
+            for (key, other) in other {
+                match value.get_mut(key) {
+                    Some(value) => merge_at(value, other, &format!("{pointer}/{key}")),
+                    None => {
+                        value.insert(key.clone(), other.clone());
+                    }
+                }
+            }
+        }
+        (value, other) => *value = other.clone(),
+    }
+}
+
+/// Return the part of `value` different from `base`.
+fn diff(value: &Value, base: &Value) -> Option<Value> {
+    diff_at(value, base, "")
+}
+
+fn diff_at(value: &Value, base: &Value, pointer: &str) -> Option<Value> {
+    match (value, base) {
+        (Value::Object(value), Value::Object(base)) if !REPLACED.contains(&pointer) => {
+            let map: Map<_, _> = value
+                .iter()
+                .filter_map(|(key, value)| match base.get(key) {
+                    Some(base) => {
+                        diff_at(value, base, &format!("{pointer}/{key}")).map(|v| (key.clone(), v))
+                    }
+                    // the missing field is same as null
+                    None if value.is_null() => None,
+                    None => Some((key.clone(), value.clone())),
+                })
+                .collect();
+
+            (!map.is_empty()).then_some(Value::Object(map))
+        }
+        (value, base) if value == base => None,
+        (value, _) => Some(value.clone()),
+    }
+}
+
+/// The enums can be written in different names, convert them to the serialized one.
+fn normalize(base: &mut Value) {
+    for pointer in [
+        "/ss_cfg/method",
+        "/kcp_cfg/crypt",
+        "/kcp_cfg/mode",
+        "/restart/policy",
+        "/log/mode",
+    ] {
+        let Some(value) = base.pointer_mut(pointer) else {
+            continue;
+        };
+        let Some(name) = value.as_str() else {
+            continue;
+        };
+        let converted = match pointer {
+            "/ss_cfg/method" => Method::try_from(name).ok().map(|v| serde_json::json!(v)),
+            "/kcp_cfg/crypt" => Crypt::try_from(name).ok().map(|v| serde_json::json!(v)),
+            "/kcp_cfg/mode" => KcpMode::try_from(name).ok().map(|v| serde_json::json!(v)),
+            "/log/mode" => LogMode::try_from(name).ok().map(|v| serde_json::json!(v)),
+            _ => RestartPolicy::try_from(name)
+                .ok()
+                .map(|v| serde_json::json!(v)),
+        };
+
+        if let Some(converted) = converted {
+            *value = converted;
+        }
+    }
+}
+
+/// Return the path of first null value, such as `kcp_cfg`.
+pub fn find_null(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => Some(String::new()),
+        Value::Object(map) => map.iter().find_map(|(key, value)| {
+            find_null(value).map(|v| match v.is_empty() {
+                true => key.clone(),
+                false => format!("{key}.{v}"),
+            })
+        }),
+        _ => None,
+    }
+}
@@ -1,18 +1,37 @@
+pub mod capture;
 pub mod config;
+pub mod daemon;
 pub mod helper;
+pub mod inherit;
 pub mod manager;
+pub mod procfs;
 pub mod proxy;
+pub mod rotate;
 pub mod splitted;
+pub mod state;
+pub mod supervisor;
+pub mod template;
+pub mod validate;
 
 use std::path::PathBuf;
 
 use cote::prelude::*;
+use nix::sys::termios::tcgetattr;
+use nix::sys::termios::tcsetattr;
+use nix::sys::termios::SetArg;
 use rustyline::error::ReadlineError;
+use rustyline::CompletionType;
+use rustyline::Config;
 use rustyline::Editor;
+use tokio::select;
+use tokio::signal::unix::signal;
+use tokio::signal::unix::SignalKind;
 use tokio::spawn;
 use tokio::sync::mpsc::channel;
 use tokio::task::spawn_blocking;
 
+use daemon::Client;
+use daemon::Failure;
 use helper::DeployHelper;
 use manager::AppContext;
 use manager::Manager;
@@ -26,18 +45,180 @@ use proxy::Server;
 pub struct DeployCli {
     #[arg(value = "history.txt")]
     history: Option<PathBuf>,
+
+    /// Set the path of daemon control socket
+    #[arg(alias = "-s")]
+    socket: Option<PathBuf>,
+
+    /// Set the directory of instance state files
+    state: Option<PathBuf>,
+
+    /// Set the default directory of instance logs
+    log_dir: Option<PathBuf>,
+
+    /// Load the deploy configurations before running commands
+    #[arg(alias = "-c")]
+    config: Option<String>,
+
+    /// Run the commands in script file line by line, then exit
+    script: Option<PathBuf>,
+
+    /// Continue running the script after a command failed
+    keep_going: bool,
+}
+
+/// Commands can be run from command line, `daemon` and the ones of [`Manager`].
+pub fn commands() -> Vec<String> {
+    let mut cmds = vec![String::from("daemon")];
+
+    if let Ok(parser) = Manager::into_parser() {
+        for opt in parser.optset().iter().filter(|v| v.mat_style(Style::Cmd)) {
+            cmds.push(opt.name().to_string());
+            cmds.extend(opt.alias().into_iter().flatten().cloned());
+        }
+    }
+    cmds
+}
+
+/// The REPL either owns the [`AppContext`] or attaches to a daemon.
+#[derive(Debug)]
+pub enum Backend {
+    Local(Box<AppContext>),
+
+    Remote(Client),
+}
+
+impl Backend {
+    pub async fn invoke_cmd(&mut self, line: &str) -> color_eyre::Result<()> {
+        let splitted = splitted::Splitted::new(line);
+        let args = splitted.split_args(None).args;
+
+        match self {
+            Backend::Local(ctx) => {
+                Manager::invoke_cmd(args, ctx).await?;
+                ctx.run_follow().await
+            }
+            // help is printed by the parser directly, display it in the client
+            Backend::Remote(_) if is_help(&args) => {
+                Manager::invoke_cmd(args, &mut AppContext::default()).await
+            }
+            Backend::Remote(client) => {
+                client
+                    .invoke_cmd(line.to_string(), &mut std::io::stdout())
+                    .await
+            }
+        }
+    }
+
+    /// Number of instances that would be left running on exit.
+    pub fn running(&self) -> usize {
+        match self {
+            Backend::Local(ctx) => ctx.insts.len(),
+            Backend::Remote(_) => 0,
+        }
+    }
+
+    /// Stop all the instances owned by the REPL.
+    pub async fn shutdown(&mut self) -> color_eyre::Result<()> {
+        match self {
+            Backend::Local(ctx) => ctx.shutdown().await,
+            Backend::Remote(_) => Ok(()),
+        }
+    }
+
+    pub async fn reply(&mut self, req: Request) -> color_eyre::Result<Reply> {
+        match self {
+            Backend::Local(ctx) => Ok(ctx.reply(req)),
+            Backend::Remote(client) => client.request(req).await,
+        }
+    }
+}
+
+fn is_help(args: &[&str]) -> bool {
+    args.first() == Some(&"help") || args.iter().any(|v| matches!(*v, "-h" | "-?" | "--help"))
 }
 
 #[derive(Debug)]
 pub enum Message {
     Interrupted,
     Line(String),
+    Answer(String),
     Report(String),
     Request(Request),
 }
 
+/// What the readline should read next.
+#[derive(Debug)]
+pub enum Prompt {
+    Command,
+
+    /// Ask the user how to exit with instances running
+    Confirm(String),
+}
+
 impl DeployCli {
-    pub async fn main(&self) -> color_eyre::Result<()> {
+    /// Run the REPL, or the commands given in command line and return the exit code.
+    pub async fn main(&self, cmd: Vec<String>) -> color_eyre::Result<i32> {
+        let socket = match self.socket.clone() {
+            Some(socket) => socket,
+            None => daemon::default_socket()?,
+        };
+        let state = self.state.clone().unwrap_or_else(state::default_state_dir);
+
+        if cmd.first().map(String::as_str) == Some("daemon") {
+            let mut ctx = AppContext::with_state(state)?;
+
+            // the relative paths of clients are resolved against their directories
+            ctx.log_dir = self
+                .log_dir
+                .as_deref()
+                .map(std::path::absolute)
+                .transpose()?;
+            ctx.capture = true;
+            daemon::serve(&socket, ctx).await?;
+            return Ok(0);
+        }
+
+        let batch = !cmd.is_empty() || self.script.is_some();
+        let mut backend = match Client::connect(&socket).await {
+            Ok(client) => {
+                if !batch {
+                    println!("Attached to daemon at `{}`", socket.display());
+                }
+                Backend::Remote(client)
+            }
+            Err(e) => {
+                if e.kind() == std::io::ErrorKind::PermissionDenied {
+                    eprintln!("WARN! Not attach to daemon: {e}");
+                }
+                let mut ctx = AppContext::with_state(state)?;
+
+                ctx.log_dir = self.log_dir.clone();
+
+                if !batch && !ctx.insts.is_empty() {
+                    println!("Adopted {} running instance(s)", ctx.insts.len());
+                }
+                Backend::Local(Box::new(ctx))
+            }
+        };
+
+        let load = match &self.config {
+            // the daemon may run in another directory
+            Some(config) => Some(format!(
+                "load -c {}",
+                std::path::absolute(shellexpand::full(config)?.as_ref())?.display()
+            )),
+            None => None,
+        };
+
+        if batch {
+            return self.run_batch(&mut backend, load, cmd).await;
+        }
+        if let Some(load) = load {
+            if let Err(e) = backend.invoke_cmd(&load).await {
+                eprintln!("Got error: {e:?}")
+            }
+        }
         let (
             proxy_cli,
             Server {
@@ -46,12 +227,17 @@ impl DeployCli {
             },
         ) = proxy::<Reply, Request>(32);
 
-        let mut ctx = AppContext::default();
-        let mut readline = Editor::<DeployHelper, _>::new()?;
+        // the readline may be in raw mode when terminated by signal
+        let termios = tcgetattr(std::io::stdin()).ok();
+        // list the candidates with description
+        let config = Config::builder()
+            .completion_type(CompletionType::List)
+            .build();
+        let mut readline = Editor::<DeployHelper, _>::with_config(config)?;
 
         let history = self.history.clone();
 
-        let (rl_start_tx, mut rl_start_rx) = channel::<()>(16);
+        let (rl_start_tx, mut rl_start_rx) = channel::<Prompt>(16);
         let (req_server_tx, mut message_rx) = channel(32);
         let readline_tx = req_server_tx.clone();
 
@@ -70,21 +256,43 @@ impl DeployCli {
                     eprintln!("WARN! Failed load history file `{}`: {e:?}", path.display());
                 }
             }
-            while let Some(()) = rl_start_rx.blocking_recv() {
-                let ret = readline.readline(&prompt);
+            while let Some(next) = rl_start_rx.blocking_recv() {
+                let question = match next {
+                    Prompt::Command => None,
+                    Prompt::Confirm(question) => Some(question),
+                };
+                let ret = readline.readline(question.as_deref().unwrap_or(&prompt));
 
                 match ret {
+                    Ok(line) if question.is_some() => {
+                        readline_tx.blocking_send(Message::Answer(line.trim().to_string()))?;
+                    }
                     Ok(line) => {
                         let line = line.trim().to_string();
 
                         if !line.is_empty() {
                             readline.add_history_entry(line.clone())?;
+                            // the process may be terminated by signal before exit the loop
+                            if let Some(path) = &history {
+                                if let Err(e) = readline.append_history(path) {
+                                    eprintln!(
+                                        "WARN! Failed save history file `{}`: {e:?}",
+                                        path.display()
+                                    );
+                                }
+                            }
                         }
                         readline_tx.blocking_send(Message::Line(line))?;
                     }
-                    Err(ReadlineError::Interrupted) => {
+                    // cancel the exit with ctrl-c, end of input leaves the instances running
+                    Err(ReadlineError::Interrupted) if question.is_some() => {
+                        readline_tx.blocking_send(Message::Answer(String::from("c")))?;
+                    }
+                    Err(ReadlineError::Eof) if question.is_some() => {
+                        readline_tx.blocking_send(Message::Answer(String::from("d")))?;
+                    }
+                    Err(ReadlineError::Interrupted | ReadlineError::Eof) => {
                         readline_tx.blocking_send(Message::Interrupted)?;
-                        break;
                     }
                     Err(e) => {
                         readline_tx.blocking_send(Message::Report(format!("Got error: {e:?}")))?;
@@ -107,55 +315,130 @@ impl DeployCli {
             Ok::<_, color_eyre::Report>(())
         });
 
-        let mut ready_readline = true;
+        let mut next_prompt = Some(Prompt::Command);
+        let mut sigterm = signal(SignalKind::terminate())?;
+        let mut sighup = signal(SignalKind::hangup())?;
 
         // process message
         loop {
-            if ready_readline {
+            if let Some(prompt) = next_prompt.take() {
                 // start readline
-                rl_start_tx.send(()).await?;
-                ready_readline = false;
+                rl_start_tx.send(prompt).await?;
             }
-            if let Some(msg) = message_rx.recv().await {
+
+            let msg = select! {
+                msg = message_rx.recv() => msg,
+                _ = sigterm.recv() => None,
+                _ = sighup.recv() => None,
+            };
+
+            if let Some(msg) = msg {
                 match msg {
                     Message::Line(line) => {
-                        let splitted = splitted::Splitted::new(&line);
-                        let args = splitted.split_args(None).args;
-
-                        if let Err(e) = Manager::invoke_cmd(args, &mut ctx).await {
+                        if let Err(e) = backend.invoke_cmd(&line).await {
                             eprintln!("Got error: {e:?}")
                         }
-                        ready_readline = true;
+                        next_prompt = Some(Prompt::Command);
                     }
                     Message::Interrupted => {
-                        break;
+                        let running = backend.running();
+
+                        if running == 0 {
+                            break;
+                        }
+                        next_prompt = Some(Prompt::Confirm(format!(
+                            "{running} instance(s) still running, [d]etach, [s]top all or [c]ancel? "
+                        )));
                     }
+                    Message::Answer(answer) => match answer.as_str() {
+                        "d" | "detach" => break,
+                        "s" | "stop" => {
+                            backend.shutdown().await?;
+                            break;
+                        }
+                        _ => next_prompt = Some(Prompt::Command),
+                    },
                     Message::Report(msg) => {
-                        ready_readline = true;
+                        next_prompt = Some(Prompt::Command);
                         eprintln!("{msg}");
                     }
-                    Message::Request(req) => match req {
-                        Request::FetchInstanceId => {
-                            proxy_tx
-                                .send(Reply::InstanceId(
-                                    ctx.insts.iter().map(|v| v.id).collect::<Vec<_>>(),
-                                ))
-                                .await?;
-                        }
-                        Request::FetchTaskIndex => {
-                            proxy_tx
-                                .send(Reply::TaskIndex((0..ctx.cfgs.len()).collect::<Vec<_>>()))
-                                .await?;
-                        }
-                    },
+                    Message::Request(req) => {
+                        proxy_tx.send(backend.reply(req).await?).await?;
+                    }
+                }
+            } else {
+                // terminated by signal, the readline is still waiting for input,
+                // the history is already saved
+                backend.shutdown().await?;
+                if let Some(termios) = &termios {
+                    let _ = tcsetattr(std::io::stdin(), SetArg::TCSANOW, termios);
                 }
+                std::process::exit(0);
             }
         }
 
         drop(rl_start_tx);
         background_rl_handler.await??;
 
-        Ok(())
+        Ok(0)
+    }
+
+    /// Run the lines of script and the command, stop at the first failure
+    /// unless `--keep-going` set.
+    async fn run_batch(
+        &self,
+        backend: &mut Backend,
+        load: Option<String>,
+        cmd: Vec<String>,
+    ) -> color_eyre::Result<i32> {
+        let mut lines = vec![];
+        let mut code = 0;
+
+        if let Some(load) = load {
+            lines.push((String::from("--config"), load));
+        }
+        if let Some(script) = &self.script {
+            let content = std::fs::read_to_string(script)?;
+
+            for (index, line) in content.lines().enumerate() {
+                let line = line.trim();
+
+                // skip the empty lines and comments
+                if !line.is_empty() && !line.starts_with('#') {
+                    lines.push((
+                        format!("{}:{}", script.display(), index + 1),
+                        line.to_string(),
+                    ));
+                }
+            }
+        }
+        if !cmd.is_empty() {
+            lines.push((String::from("command line"), cmd.join(" ")));
+        }
+        for (at, line) in lines {
+            if let Err(e) = backend.invoke_cmd(&line).await {
+                eprintln!("Command `{line}` failed at {at}: {e:#}");
+                if code == 0 {
+                    code = exit_code(&e);
+                }
+                if !self.keep_going {
+                    break;
+                }
+            }
+        }
+
+        Ok(code)
+    }
+}
+
+/// Exit code of failed command, 2 for the invalid arguments.
+fn exit_code(e: &color_eyre::Report) -> i32 {
+    let failure = e.downcast_ref::<Failure>();
+
+    if e.downcast_ref::<cote::Error>().is_some() || failure.is_some_and(|v| v.invalid_args) {
+        2
+    } else {
+        1
     }
 }
 
@@ -163,7 +446,48 @@ impl DeployCli {
 async fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
 
-    DeployCli::parse_env()?.main().await?;
+    let (args, cmd) = split_command(std::env::args());
+
+    let code = DeployCli::parse(Args::from(args))?.main(cmd).await?;
 
+    if code != 0 {
+        std::process::exit(code);
+    }
     Ok(())
 }
+
+/// Options of [`DeployCli`] followed by a value.
+fn value_options() -> Vec<String> {
+    let mut opts = vec![];
+
+    if let Ok(parser) = DeployCli::into_parser() {
+        for opt in parser
+            .optset()
+            .iter()
+            .filter(|v| v.mat_style(Style::Argument))
+        {
+            opts.push(opt.name().to_string());
+            opts.extend(opt.alias().into_iter().flatten().cloned());
+        }
+    }
+    opts
+}
+
+/// Split the command line at the first command name.
+fn split_command(args: impl Iterator<Item = String>) -> (Vec<String>, Vec<String>) {
+    let mut args: Vec<_> = args.collect();
+    let cmds = commands();
+    let opts = value_options();
+    let mut pos = 1;
+
+    while let Some(arg) = args.get(pos) {
+        if cmds.contains(arg) {
+            break;
+        }
+        // the value of option may be same as the command name
+        pos += if opts.contains(arg) { 2 } else { 1 };
+    }
+    let cmd = args.split_off(pos.min(args.len()));
+
+    (args, cmd)
+}
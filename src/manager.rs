@@ -1,50 +1,418 @@
+mod add;
+mod check;
+mod clone;
+mod export;
+mod format;
+mod genkey;
 mod help;
+mod import;
 mod kill;
 mod list;
 mod load;
+mod logs;
+mod remove;
+mod save;
+mod set;
 mod start;
+mod status;
+
+use std::future::Future;
+use std::io::IsTerminal;
+use std::io::Write;
+use std::os::fd::AsFd;
+use std::path::Path;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
 
 use cote::prelude::*;
 use help::Help;
-use tokio::process::Child;
+use nix::poll::poll;
+use nix::poll::PollFd;
+use nix::poll::PollFlags;
+use nix::poll::PollTimeout;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::sync::mpsc::unbounded_channel;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::oneshot;
 
 use crate::config::DeployConfig;
+use crate::inherit::Inherit;
+use crate::supervisor::Supervisor;
 
-use kill::Kill;
 use list::List;
 
+pub use add::Add;
+pub use check::Check;
+pub use clone::CloneConfig;
+pub use export::ExportClient;
+pub use genkey::GenKey;
+pub use import::Import;
+pub use kill::Kill;
+pub use kill::DEFAULT_KILL_TIMEOUT;
 pub use load::Load;
 pub use load::DEFAULT_CONFIG;
+pub use logs::Logs;
+pub use remove::Remove;
+pub use save::Save;
+pub use set::SetField;
+pub use start::KcpFlags;
 pub use start::Start;
+pub use status::Status;
 
 #[derive(Debug)]
 pub struct SsInstance {
+    /// Unique id of the instance
     pub id: usize,
 
-    pub ss: Child,
+    /// Index of the deploy configuration
+    pub index: usize,
+
+    pub supervisor: Supervisor,
+}
+
+/// Where the commands write their output.
+#[derive(Debug, Clone, Default)]
+pub enum Output {
+    #[default]
+    Stdout,
+
+    /// Forward the output to a client of daemon
+    Channel(UnboundedSender<String>),
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Output::Stdout => std::io::stdout().write(buf),
+            Output::Channel(sender) => {
+                sender
+                    .send(String::from_utf8_lossy(buf).into_owned())
+                    .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+                Ok(buf.len())
+            }
+        }
+    }
 
-    pub kcp: Option<Child>,
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Output::Stdout => std::io::stdout().flush(),
+            Output::Channel(_) => Ok(()),
+        }
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct AppContext {
     pub cfgs: Vec<DeployConfig>,
 
+    /// Path of the loaded configurations
+    pub path: Option<String>,
+
+    /// Defaults and profiles of the loaded configurations
+    pub inherit: Inherit,
+
     pub insts: Vec<SsInstance>,
+
+    pub out: Output,
+
+    /// Directory of the instance state files
+    pub state: Option<PathBuf>,
+
+    /// Directory of the logs not set by `start` or configuration
+    pub log_dir: Option<PathBuf>,
+
+    /// Working directory of the client attached to daemon
+    pub cwd: Option<PathBuf>,
+
+    /// Keep the output of instances without log path in memory and allow the log rotation,
+    /// only the daemon does it as the pipes are closed when rssdeploy exits
+    pub capture: bool,
+
+    /// Id of the next instance
+    pub next_id: usize,
+
+    /// Cancel signal of the running command, ctrl-c is used if not set
+    pub cancel: Option<oneshot::Receiver<()>>,
+
+    /// The long running part of the last command, such as `logs --follow`
+    pub follow: Option<Follow>,
+
+    /// The functions sent by [`Follow`] to run with the context
+    pub access: AccessQueue,
+}
+
+/// The long running part of command, it runs outside of the context,
+/// so the daemon can serve other clients meanwhile. Use [`Access`] to read the context.
+pub struct Follow(Pin<Box<dyn Future<Output = color_eyre::Result<()>> + Send>>);
+
+impl Follow {
+    pub fn new(fut: impl Future<Output = color_eyre::Result<()>> + Send + 'static) -> Self {
+        Self(Box::pin(fut))
+    }
+}
+
+impl std::fmt::Debug for Follow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Follow").finish_non_exhaustive()
+    }
 }
 
+impl Future for Follow {
+    type Output = color_eyre::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0.as_mut().poll(cx)
+    }
+}
+
+pub type AccessFn = Box<dyn FnOnce(&mut AppContext) + Send>;
+
+/// Run the functions with the context from [`Follow`].
 #[derive(Debug, Clone)]
+pub struct Access(UnboundedSender<AccessFn>);
+
+impl Access {
+    /// Run `f` with the context and return its result.
+    pub async fn run<T, F>(&self, f: F) -> color_eyre::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut AppContext) -> T + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+
+        self.0
+            .send(Box::new(move |ac| {
+                let _ = tx.send(f(ac));
+            }))
+            .map_err(|_| color_eyre::Report::msg("The context is closed"))?;
+        Ok(rx.await?)
+    }
+}
+
+/// The channel of [`Access`], the receiver is taken by whom owns the context.
+#[derive(Debug)]
+pub struct AccessQueue {
+    pub tx: Access,
+
+    pub rx: Option<UnboundedReceiver<AccessFn>>,
+}
+
+impl Default for AccessQueue {
+    fn default() -> Self {
+        let (tx, rx) = unbounded_channel();
+
+        Self {
+            tx: Access(tx),
+            rx: Some(rx),
+        }
+    }
+}
+
+impl AppContext {
+    /// Create the context and adopt the instances recorded in the state directory.
+    pub fn with_state(dir: PathBuf) -> color_eyre::Result<Self> {
+        let insts = crate::state::adopt(&dir)?;
+        let next_id = crate::state::load_next_id(&dir)
+            .max(insts.iter().map(|v| v.id + 1).max().unwrap_or_default());
+
+        Ok(Self {
+            insts,
+            state: Some(dir),
+            next_id,
+            ..Default::default()
+        })
+    }
+
+    /// Allocate an instance id, the id is increased monotonically.
+    pub fn alloc_id(&mut self) -> color_eyre::Result<usize> {
+        let mut id = self.next_id;
+
+        if let Some(dir) = &self.state {
+            // the ids may be allocated by other rssdeploy using the same directory
+            let _lock = crate::state::lock(dir)?;
+
+            id = id.max(crate::state::load_next_id(dir));
+            crate::state::save_next_id(dir, id + 1)?;
+        }
+        self.next_id = id + 1;
+        Ok(id)
+    }
+
+    /// Resolve the relative path given by the client attached to daemon against its working directory.
+    pub fn path(&self, path: impl AsRef<Path>) -> color_eyre::Result<PathBuf> {
+        let path = path.as_ref();
+        let Some(cwd) = &self.cwd else {
+            return Ok(path.to_path_buf());
+        };
+        let expanded = shellexpand::path::full(path)?;
+
+        // keep the variables of path if it is not relative
+        Ok(if expanded.is_relative() {
+            cwd.join(expanded)
+        } else {
+            path.to_path_buf()
+        })
+    }
+
+    /// Same as [`AppContext::path`], but keep the executable name searched in `PATH`.
+    pub fn bin_path(&self, path: impl AsRef<Path>) -> color_eyre::Result<PathBuf> {
+        let path = path.as_ref();
+
+        if path.components().count() == 1 && path.is_relative() {
+            Ok(path.to_path_buf())
+        } else {
+            self.path(path)
+        }
+    }
+
+    /// Run the [`Follow`] of last command, and serve its [`Access`] until it done.
+    pub async fn run_follow(&mut self) -> color_eyre::Result<()> {
+        let Some(mut follow) = self.follow.take() else {
+            return Ok(());
+        };
+        let Some(mut rx) = self.access.rx.take() else {
+            return follow.await;
+        };
+        let ret = loop {
+            tokio::select! {
+                ret = &mut follow => break ret,
+                Some(access) = rx.recv() => access(self),
+            }
+        };
+
+        self.access.rx = Some(rx);
+        ret
+    }
+
+    /// Stop all the instances gracefully.
+    pub async fn shutdown(&mut self) -> color_eyre::Result<()> {
+        Kill {
+            all: true,
+            id: None,
+            timeout: Some(DEFAULT_KILL_TIMEOUT),
+        }
+        .invoke_cmd(self)
+        .await
+    }
+
+    /// Answer the completion request of readline.
+    pub fn reply(&self, req: Request) -> Reply {
+        match req {
+            Request::FetchInstanceId => Reply::InstanceId(
+                self.insts
+                    .iter()
+                    .map(|v| {
+                        let ss = v.supervisor.ss();
+                        let state = match ss.pid {
+                            Some(_) => format!("up {}", format_duration(ss.uptime())),
+                            None => String::from("exited"),
+                        };
+
+                        (v.id, format!("config {}, {state}", v.index))
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+            Request::FetchTaskIndex => Reply::TaskIndex(
+                self.cfgs
+                    .iter()
+                    .enumerate()
+                    .map(|(index, cfg)| {
+                        let kcp = if cfg.kcp_cfg.is_some() {
+                            ", kcptun"
+                        } else {
+                            ""
+                        };
+
+                        (
+                            index,
+                            format!(
+                                "port {}, {}{kcp}",
+                                cfg.ss_cfg.server_port, cfg.ss_cfg.method
+                            ),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+        }
+    }
+}
+
+/// Wait until the running command is cancelled by user, by the `cancel` of [`AppContext`]
+/// or ctrl-c and enter if it is not set.
+pub async fn cancelled(cancel: &mut Option<oneshot::Receiver<()>>) {
+    match cancel.as_mut() {
+        Some(cancel) => {
+            let _ = cancel.await;
+        }
+        None => {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => { }
+                _ = enter_pressed() => { }
+            }
+        }
+    }
+}
+
+/// Wait until the enter key is pressed, never return if stdin is not a terminal.
+///
+/// The stdin is polled instead of read in background, which would take the input of readline.
+pub async fn enter_pressed() {
+    let stdin = std::io::stdin();
+
+    if !stdin.is_terminal() {
+        return std::future::pending().await;
+    }
+    loop {
+        let mut fds = [PollFd::new(stdin.as_fd(), PollFlags::POLLIN)];
+
+        if poll(&mut fds, PollTimeout::ZERO).is_ok_and(|v| v > 0) {
+            let mut buf = [0; 1024];
+
+            // the line is ready, the read is not blocked
+            let _ = nix::unistd::read(stdin.as_fd(), &mut buf);
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// Format the duration as `1d2h3m4s`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (days, hours, mins, secs) = (
+        secs / 86400,
+        secs % 86400 / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+    );
+
+    match (days, hours, mins) {
+        (0, 0, 0) => format!("{secs}s"),
+        (0, 0, _) => format!("{mins}m{secs}s"),
+        (0, _, _) => format!("{hours}h{mins}m{secs}s"),
+        _ => format!("{days}d{hours}h{mins}m{secs}s"),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Request {
     FetchInstanceId,
 
     FetchTaskIndex,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Reply {
-    InstanceId(Vec<usize>),
+    /// Instance ids with description
+    InstanceId(Vec<(usize, String)>),
 
-    TaskIndex(Vec<usize>),
+    /// Configuration indices with description
+    TaskIndex(Vec<(usize, String)>),
 }
 
 #[derive(Debug, Default, Cote)]
@@ -58,7 +426,7 @@ pub struct Manager {
     #[sub(scvalues)]
     kill: Option<Kill>,
 
-    /// Load deploy configurations from *.json
+    /// Load deploy configurations from *.json, *.toml or *.yaml
     #[sub(alias = "ld", scvalues)]
     load: Option<Load>,
 
@@ -66,6 +434,50 @@ pub struct Manager {
     #[sub(alias = "st", scvalues)]
     start: Option<Start>,
 
+    /// Set the field of configuration
+    #[sub(scvalues)]
+    set: Option<SetField>,
+
+    /// Add a new configuration
+    #[sub(scvalues)]
+    add: Option<Add>,
+
+    /// Remove the configuration by index
+    #[sub(alias = "rm", scvalues)]
+    remove: Option<Remove>,
+
+    /// Clone the configuration by index
+    #[sub(name = "clone", scvalues)]
+    clone_config: Option<CloneConfig>,
+
+    /// Save the configurations to the loaded path
+    #[sub(scvalues)]
+    save: Option<Save>,
+
+    /// Check the deploy configurations
+    #[sub(scvalues)]
+    check: Option<Check>,
+
+    /// Export the client configuration and ss:// URI
+    #[sub(name = "export-client", scvalues)]
+    export_client: Option<ExportClient>,
+
+    /// Generate random keys for the methods and kcptun
+    #[sub(scvalues)]
+    genkey: Option<GenKey>,
+
+    /// Import configurations from ss:// URI or configuration of ssserver and kcptun
+    #[sub(scvalues)]
+    import: Option<Import>,
+
+    /// Show the live status of instances
+    #[sub(scvalues)]
+    status: Option<Status>,
+
+    /// Show the output of instance
+    #[sub(scvalues)]
+    logs: Option<Logs>,
+
     /// Display the help of given command
     #[sub(scvalues)]
     help: Option<Help>,
@@ -73,7 +485,7 @@ pub struct Manager {
 
 impl Manager {
     pub async fn invoke_cmd(args: Vec<&str>, ac: &mut AppContext) -> color_eyre::Result<()> {
-        let args: Vec<_> = std::iter::once("app").chain(args.into_iter()).collect();
+        let args: Vec<_> = std::iter::once("app").chain(args).collect();
         let manager = Manager::parse(Args::from(args))?;
 
         if let Some(list) = manager.list {
@@ -84,6 +496,28 @@ impl Manager {
             load.invoke_cmd(ac).await?;
         } else if let Some(start) = manager.start {
             start.invoke_cmd(ac).await?;
+        } else if let Some(set) = manager.set {
+            set.invoke_cmd(ac).await?;
+        } else if let Some(add) = manager.add {
+            add.invoke_cmd(ac).await?;
+        } else if let Some(remove) = manager.remove {
+            remove.invoke_cmd(ac).await?;
+        } else if let Some(clone_config) = manager.clone_config {
+            clone_config.invoke_cmd(ac).await?;
+        } else if let Some(save) = manager.save {
+            save.invoke_cmd(ac).await?;
+        } else if let Some(check) = manager.check {
+            check.invoke_cmd(ac).await?;
+        } else if let Some(export_client) = manager.export_client {
+            export_client.invoke_cmd(ac).await?;
+        } else if let Some(genkey) = manager.genkey {
+            genkey.invoke_cmd(ac).await?;
+        } else if let Some(import) = manager.import {
+            import.invoke_cmd(ac).await?;
+        } else if let Some(status) = manager.status {
+            status.invoke_cmd(ac).await?;
+        } else if let Some(logs) = manager.logs {
+            logs.invoke_cmd(ac).await?;
         } else if let Some(help) = manager.help {
             help.invoke_cmd(ac).await?;
         }
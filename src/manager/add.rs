@@ -0,0 +1,135 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use color_eyre::eyre::eyre;
+use cote::prelude::*;
+
+use crate::config::DeployConfig;
+use crate::config::KcpConfig;
+use crate::config::Method;
+use crate::config::SsConfig;
+
+use super::genkey::gen_key;
+use super::import::DEFAULT_BIN;
+use super::import::DEFAULT_KCP;
+use super::set::print_issues;
+use super::AppContext;
+
+#[derive(Debug, Cote)]
+#[cote(shellcomp, aborthelp, width = 50, overload, notexit)]
+pub struct Add {
+    /// Set the listen port of ssserver, default is next to the largest port
+    #[arg(alias = "-p")]
+    pub port: Option<u32>,
+
+    /// Set the password of ssserver, default is a random key
+    pub password: Option<String>,
+
+    /// Set the method of ssserver
+    #[arg(alias = "-m", scvalues = Method::values())]
+    pub method: Option<Method>,
+
+    /// Set the path of ssserver
+    pub bin: Option<PathBuf>,
+
+    /// Set the path of kcptun
+    pub kcp: Option<PathBuf>,
+
+    /// Add the kcptun configuration with a random key
+    #[arg(alias = "-k")]
+    pub enable_kcp: bool,
+}
+
+impl Add {
+    pub async fn invoke_cmd(&self, ac: &mut AppContext) -> color_eyre::Result<()> {
+        let method = self.method.unwrap_or_default();
+        let password = match &self.password {
+            Some(password) => password.clone(),
+            None => gen_key(&method)?,
+        };
+        let kcp_cfg = if self.enable_kcp {
+            Some(KcpConfig {
+                key: gen_key(&Method::None)?.into(),
+                ..KcpConfig::kcptun_default()
+            })
+        } else {
+            None
+        };
+        let index = ac.cfgs.len();
+        let port = match self.port {
+            Some(port) => port,
+            None => next_port(&ac.cfgs)?,
+        };
+
+        ac.cfgs.push(DeployConfig {
+            bin: match &self.bin {
+                Some(bin) => ac.bin_path(bin)?,
+                None => PathBuf::from(DEFAULT_BIN),
+            },
+            kcp: match &self.kcp {
+                Some(kcp) => ac.bin_path(kcp)?,
+                None => PathBuf::from(DEFAULT_KCP),
+            },
+            ss_cfg: SsConfig {
+                server: String::from("0.0.0.0"),
+                server_port: port,
+                password: password.into(),
+                timeout: 300,
+                method,
+                fast_open: false,
+            },
+            kcp_cfg,
+            ..Default::default()
+        });
+        writeln!(
+            ac.out,
+            "Added configuration {index}: port {}, {method}",
+            ac.cfgs[index].ss_cfg.server_port
+        )?;
+        print_issues(ac, index)?;
+
+        Ok(())
+    }
+}
+
+/// The port after the largest one, skip the kcptun port `{port + 1}`.
+pub fn next_port(cfgs: &[DeployConfig]) -> color_eyre::Result<u32> {
+    let Some(largest) = cfgs.iter().map(|v| v.ss_cfg.server_port).max() else {
+        return Ok(8388);
+    };
+
+    largest
+        .checked_add(2)
+        .filter(|v| *v <= u16::MAX as u32)
+        .ok_or_else(|| eyre!("No port after the largest port {largest}, set it by `--port`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configs(ports: &[u32]) -> Vec<DeployConfig> {
+        ports
+            .iter()
+            .map(|port| {
+                let mut cfg = DeployConfig::default();
+
+                cfg.ss_cfg.server_port = *port;
+                cfg
+            })
+            .collect()
+    }
+
+    #[test]
+    fn next_port_after_largest() {
+        assert_eq!(next_port(&[]).unwrap(), 8388);
+        assert_eq!(next_port(&configs(&[8390, 8388])).unwrap(), 8392);
+        assert_eq!(next_port(&configs(&[65533])).unwrap(), 65535);
+    }
+
+    #[test]
+    fn next_port_out_of_range() {
+        assert!(next_port(&configs(&[65534])).is_err());
+        assert!(next_port(&configs(&[u32::MAX])).is_err());
+    }
+}
@@ -0,0 +1,61 @@
+use std::io::Write;
+
+use cote::prelude::*;
+use prettytable::{Row, Table};
+
+use crate::validate::validate;
+use crate::validate::Issue;
+use crate::validate::Level;
+
+use super::format::Format;
+use super::load::read_configs;
+use super::AppContext;
+
+#[derive(Debug, Cote)]
+#[cote(shellcomp, aborthelp, width = 50, overload, notexit)]
+pub struct Check {
+    /// Check the configuration file instead of the loaded configurations
+    #[arg(alias = "-c")]
+    pub config: Option<String>,
+
+    /// Set the output format: table, json or yaml
+    #[arg(alias = "-f", scvalues = Format::values())]
+    pub format: Option<Format>,
+}
+
+impl Check {
+    pub async fn invoke_cmd(&self, ac: &mut AppContext) -> color_eyre::Result<()> {
+        let issues = match &self.config {
+            Some(path) => validate(&read_configs(&ac.path(path)?.display().to_string()).await?.0),
+            None => validate(&ac.cfgs),
+        };
+        let format = self.format.unwrap_or_default();
+
+        if format != Format::Table {
+            format.write(&mut ac.out, &issues)?;
+        } else if issues.is_empty() {
+            writeln!(ac.out, "No problem found")?;
+        } else {
+            let mut table = Table::new();
+
+            table.add_row(Row::from(["Level", "Path", "Message"]));
+            for issue in issues.iter() {
+                table.add_row(Row::from(vec![
+                    issue.level.to_string(),
+                    issue.path.clone(),
+                    issue.message.clone(),
+                ]));
+            }
+            table.print(&mut ac.out)?;
+        }
+
+        match count_errors(&issues) {
+            0 => Ok(()),
+            n => Err(color_eyre::Report::msg(format!("{n} error(s) found"))),
+        }
+    }
+}
+
+pub fn count_errors(issues: &[Issue]) -> usize {
+    issues.iter().filter(|v| v.level == Level::Error).count()
+}
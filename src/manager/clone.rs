@@ -0,0 +1,44 @@
+use std::io::Write;
+
+use cote::prelude::*;
+
+use super::add::next_port;
+use super::set::print_issues;
+use super::AppContext;
+
+#[derive(Debug, Cote)]
+#[cote(shellcomp, aborthelp, width = 50, overload, notexit)]
+pub struct CloneConfig {
+    /// Set the listen port of the copy, default is next to the largest port
+    #[arg(alias = "-p")]
+    pub port: Option<u32>,
+
+    /// The index of configuration
+    #[pos()]
+    pub index: usize,
+}
+
+impl CloneConfig {
+    pub async fn invoke_cmd(&self, ac: &mut AppContext) -> color_eyre::Result<()> {
+        let mut deploy_cfg = ac.cfgs.get(self.index).cloned().ok_or_else(|| {
+            color_eyre::Report::msg(
+                "Index out of bound, load the configurations using command `load`",
+            )
+        })?;
+        let index = ac.cfgs.len();
+
+        deploy_cfg.ss_cfg.server_port = match self.port {
+            Some(port) => port,
+            None => next_port(&ac.cfgs)?,
+        };
+        writeln!(
+            ac.out,
+            "Cloned configuration {} to {index}: port {}",
+            self.index, deploy_cfg.ss_cfg.server_port
+        )?;
+        ac.cfgs.push(deploy_cfg);
+        print_issues(ac, index)?;
+
+        Ok(())
+    }
+}
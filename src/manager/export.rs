@@ -0,0 +1,355 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use color_eyre::eyre::eyre;
+use cote::prelude::*;
+use qrcode::render::unicode::Dense1x2;
+use qrcode::QrCode;
+use serde_json::Value;
+use tokio::fs::read_to_string;
+
+use crate::config::default_kcp_port;
+use crate::config::KcpConfig;
+use crate::config::SsConfig;
+use crate::state::write_private;
+use crate::supervisor::ProcSpec;
+
+use super::import::parse_kcp_config;
+use super::import::parse_ss_config;
+use super::AppContext;
+use super::KcpFlags;
+
+#[derive(Debug, Cote)]
+#[cote(shellcomp, aborthelp, width = 50, overload, notexit)]
+pub struct ExportClient {
+    /// Set the public host or ip address of server
+    #[arg(alias = "-H")]
+    pub host: Option<String>,
+
+    /// Set the local port of sslocal
+    #[arg(value = 1080u32)]
+    pub local_port: Option<u32>,
+
+    /// Connect to server through kcptun
+    #[arg(alias = "-k")]
+    pub enable_kcp: bool,
+
+    /// Set the listen port of kcptun server, default is {port + 1}
+    #[arg(alias = "-l")]
+    pub listen: Option<u32>,
+
+    /// Set the name of server in the URI
+    #[arg(alias = "-n")]
+    pub name: Option<String>,
+
+    /// Print the URI as QR code
+    pub qr: bool,
+
+    /// Write the client configuration of sslocal to file
+    #[arg(alias = "-o")]
+    pub output: Option<PathBuf>,
+
+    /// Export the running instance with given id, the overrides of `start` are kept
+    #[arg(alias = "-i")]
+    pub id: Option<usize>,
+
+    /// The index of configuration
+    #[pos()]
+    pub index: Option<usize>,
+}
+
+impl ExportClient {
+    pub async fn invoke_cmd(&self, ac: &mut AppContext) -> color_eyre::Result<()> {
+        let host = self
+            .host
+            .as_ref()
+            .ok_or_else(|| color_eyre::Report::msg("Need the public host of server: --host"))?;
+        let (index, ss_cfg, kcp) = match self.id {
+            Some(id) => self.running(ac, id).await?,
+            None => self.configured(ac).await?,
+        };
+        // the client connect to kcptun server instead of ssserver
+        let port = match &kcp {
+            Some((_, listen)) => *listen,
+            None => ss_cfg.server_port,
+        };
+        let kcp = kcp.map(|v| v.0);
+        let password = ss_cfg.password.resolve().await?;
+        let plugin_opts = kcp.as_ref().map(plugin_opts);
+        let name = match &self.name {
+            Some(name) => name.clone(),
+            None => format!("rssdeploy-{index}"),
+        };
+        let uri = uri(
+            &ss_cfg,
+            &password,
+            host,
+            port,
+            plugin_opts.as_deref(),
+            &name,
+        );
+        let mut client = serde_json::json!({
+            "server": host,
+            "server_port": port,
+            "password": password,
+            "method": ss_cfg.method.to_string(),
+            "local_address": "127.0.0.1",
+            "local_port": self.local_port.unwrap_or(1080),
+            "timeout": ss_cfg.timeout,
+        });
+
+        if let Some(plugin_opts) = &plugin_opts {
+            client["plugin"] = "kcptun".into();
+            client["plugin_opts"] = plugin_opts.as_str().into();
+        }
+        let client = serde_json::to_string_pretty(&client)?;
+
+        writeln!(ac.out, "URI: {uri}")?;
+        if self.qr {
+            let qr = QrCode::new(uri.as_bytes())?
+                .render::<Dense1x2>()
+                .dark_color(Dense1x2::Light)
+                .light_color(Dense1x2::Dark)
+                .build();
+
+            writeln!(ac.out, "{qr}")?;
+        }
+        match &self.output {
+            Some(path) => {
+                // the configuration contains the password
+                write_private(&ac.path(path)?, client).await?;
+                writeln!(
+                    ac.out,
+                    "Client configuration written to `{}`",
+                    path.display()
+                )?;
+            }
+            None => writeln!(ac.out, "Client configuration:\n{client}")?,
+        }
+        if let Some(kcp) = &kcp {
+            let mut args = vec![
+                String::from("kcptun_client"),
+                String::from("-r"),
+                format!("{}:{port}", uri_host(host)),
+                String::from("-l"),
+                format!("127.0.0.1:{port}"),
+            ];
+
+            args.extend(kcp.tuning_args());
+            writeln!(ac.out, "Kcptun client command line:\n{}", args.join(" "))?;
+        }
+
+        Ok(())
+    }
+
+    /// Settings of the configuration, the kcptun listens on `--listen` or `{port + 1}`.
+    async fn configured(
+        &self,
+        ac: &AppContext,
+    ) -> color_eyre::Result<(usize, SsConfig, Option<(KcpConfig, u32)>)> {
+        let index = self.index.ok_or_else(|| {
+            color_eyre::Report::msg("Need the index of configuration or the instance id: --id")
+        })?;
+        let deploy_cfg = ac.cfgs.get(index).ok_or_else(|| {
+            color_eyre::Report::msg(
+                "Index out of bound, load the configurations using command `load`",
+            )
+        })?;
+        let ss_cfg = deploy_cfg.ss_cfg.clone();
+
+        if !self.enable_kcp {
+            return Ok((index, ss_cfg, None));
+        }
+        let cfg = deploy_cfg.kcp_cfg.as_ref().ok_or_else(|| {
+            color_eyre::Report::msg(format!("No kcptun configuration at index {index}"))
+        })?;
+        let listen = match self.listen {
+            Some(listen) => listen,
+            None => default_kcp_port(ss_cfg.server_port).ok_or_else(|| {
+                color_eyre::Report::msg(format!(
+                    "Invalid kcptun port {}, set it by `--listen`",
+                    u64::from(ss_cfg.server_port) + 1
+                ))
+            })?,
+        };
+        let mut cfg = KcpFlags::default().resolve(cfg);
+
+        cfg.key = cfg.key.resolve().await?.into();
+        Ok((index, ss_cfg, Some((cfg, listen))))
+    }
+
+    /// Settings of the running instance read from its generated configurations,
+    /// they have the overrides of `start`.
+    async fn running(
+        &self,
+        ac: &AppContext,
+        id: usize,
+    ) -> color_eyre::Result<(usize, SsConfig, Option<(KcpConfig, u32)>)> {
+        let inst = ac
+            .insts
+            .iter()
+            .find(|v| v.id == id)
+            .ok_or_else(|| eyre!("Invalid id `{id}`, no instance found"))?;
+        let (ss_cfg, _) = parse_ss_config(&generated(inst.supervisor.ss_spec()).await?)?;
+
+        if !self.enable_kcp {
+            return Ok((inst.index, ss_cfg, None));
+        }
+        let spec = inst
+            .supervisor
+            .kcp_spec()
+            .ok_or_else(|| eyre!("Instance {id} is started without kcptun"))?;
+        let (_, listen, cfg) = parse_kcp_config(&generated(spec).await?)?;
+        let listen = listen.ok_or_else(|| eyre!("Missing listen port of kcptun"))?;
+
+        Ok((inst.index, ss_cfg, Some((cfg, listen))))
+    }
+}
+
+/// Read the configuration generated for the process, which is passed by `-c`.
+async fn generated(spec: &ProcSpec) -> color_eyre::Result<Value> {
+    let path = spec
+        .args
+        .iter()
+        .position(|v| v == "-c")
+        .and_then(|v| spec.args.get(v + 1))
+        .ok_or_else(|| eyre!("No configuration passed to `{}`", spec.bin.display()))?;
+
+    Ok(serde_json::from_str(&read_to_string(path).await?)?)
+}
+
+/// SIP002 URI of the server, the userinfo of 2022 methods is percent encoded.
+pub fn uri(
+    ss_cfg: &SsConfig,
+    password: &str,
+    host: &str,
+    port: u32,
+    plugin: Option<&str>,
+    name: &str,
+) -> String {
+    let method = ss_cfg.method.to_string();
+    let userinfo = if ss_cfg.method.key_len().is_some() {
+        format!(
+            "{}:{}",
+            urlencoding::encode(&method),
+            urlencoding::encode(password)
+        )
+    } else {
+        URL_SAFE_NO_PAD.encode(format!("{method}:{password}"))
+    };
+    let mut uri = format!("ss://{userinfo}@{}:{port}", uri_host(host));
+
+    if let Some(plugin) = plugin {
+        uri.push_str("/?plugin=");
+        uri.push_str(&urlencoding::encode(&format!("kcptun;{plugin}")));
+    }
+    uri.push('#');
+    uri.push_str(&urlencoding::encode(name));
+    uri
+}
+
+/// Enclose the IPv6 address in brackets.
+fn uri_host(host: &str) -> String {
+    if host.contains(':') && !host.starts_with('[') {
+        format!("[{host}]")
+    } else {
+        host.to_string()
+    }
+}
+
+/// SIP003 plugin options of kcptun converted from the command line arguments.
+pub fn plugin_opts(cfg: &KcpConfig) -> String {
+    let escape = |v: &str| {
+        v.replace('\\', "\\\\")
+            .replace(';', "\\;")
+            .replace('=', "\\=")
+    };
+    let args = cfg.tuning_args();
+    let mut opts = vec![];
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        let name = arg.trim_start_matches('-');
+
+        // -nocomp is the only flag without value
+        if name == "nocomp" {
+            opts.push(name.to_string());
+        } else if let Some(value) = iter.next() {
+            opts.push(format!("{name}={}", escape(value)));
+        }
+    }
+    opts.join(";")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Crypt;
+    use crate::config::KcpMode;
+    use crate::config::Method;
+
+    use super::*;
+
+    fn ss_config(method: Method) -> SsConfig {
+        SsConfig {
+            method,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn uri_userinfo() {
+        let table = [
+            (
+                Method::Aes256,
+                "pass",
+                "example.com",
+                "ss://YWVzLTI1Ni1nY206cGFzcw@example.com:8388#a%20b",
+            ),
+            (
+                Method::Blake3Aes128_2022,
+                "a+b/c==",
+                "::1",
+                "ss://2022-blake3-aes-128-gcm:a%2Bb%2Fc%3D%3D@[::1]:8388#a%20b",
+            ),
+        ];
+
+        for (method, password, host, expected) in table {
+            let uri = uri(&ss_config(method), password, host, 8388, None, "a b");
+
+            assert_eq!(uri, expected);
+        }
+    }
+
+    #[test]
+    fn uri_plugin_opts() {
+        let cfg = KcpConfig {
+            crypt: Crypt::Salsa20,
+            key: "k;e=y\\".into(),
+            mode: KcpMode::Fast3,
+            comp: false,
+            ..KcpConfig::kcptun_default()
+        };
+        let opts = plugin_opts(&cfg);
+
+        assert_eq!(
+            opts,
+            r"crypt=salsa20;key=k\;e\=y\\;sndwnd=1024;rcvwnd=1024;dscp=0;datashard=10;parityshard=3;mode=fast3;mtu=1350;nocomp"
+        );
+        assert_eq!(
+            uri(
+                &ss_config(Method::Aes256),
+                "pass",
+                "h",
+                8389,
+                Some(&opts),
+                "n"
+            ),
+            format!(
+                "ss://YWVzLTI1Ni1nY206cGFzcw@h:8389/?plugin={}#n",
+                urlencoding::encode(&format!("kcptun;{opts}"))
+            )
+        );
+    }
+}
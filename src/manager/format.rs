@@ -0,0 +1,196 @@
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::io::Write;
+
+use cote::prelude::error;
+use cote::prelude::CoteOpt;
+use cote::prelude::CoteVal;
+use cote::shell::value::repeat_values;
+use cote::shell::value::Values;
+use serde::Serialize;
+
+use crate::config::DeployConfig;
+use crate::procfs::bound_ports;
+use crate::procfs::usage;
+use crate::procfs::Listen;
+use crate::supervisor::ProcState;
+
+use super::SsInstance;
+
+/// Output format of the read-only commands.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, CoteVal, CoteOpt)]
+#[coteval(mapstr = TryFrom::try_from)]
+pub enum Format {
+    #[default]
+    Table,
+
+    Json,
+
+    Yaml,
+}
+
+impl<'a> TryFrom<&'a str> for Format {
+    type Error = cote::Error;
+
+    fn try_from(val: &'a str) -> Result<Self, Self::Error> {
+        match val {
+            "table" => Ok(Self::Table),
+            "json" => Ok(Self::Json),
+            "yaml" => Ok(Self::Yaml),
+            _ => Err(error!("Unknown output format: {}", val)),
+        }
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Format::Table => "table",
+                Format::Json => "json",
+                Format::Yaml => "yaml",
+            }
+        )
+    }
+}
+
+impl Format {
+    pub fn values<O>() -> impl Values<O, Err = cote::Error> {
+        repeat_values(|_| {
+            Ok([Self::Table, Self::Json, Self::Yaml]
+                .map(|v| OsString::from(v.to_string()))
+                .to_vec())
+        })
+    }
+
+    /// Write the `value` in json or yaml format, the table is printed by command.
+    pub fn write<T: Serialize>(&self, out: &mut impl Write, value: &T) -> color_eyre::Result<()> {
+        match self {
+            Format::Table | Format::Json => {
+                writeln!(out, "{}", serde_json::to_string_pretty(value)?)?
+            }
+            Format::Yaml => write!(out, "{}", serde_yaml::to_string(value)?)?,
+        }
+        Ok(())
+    }
+}
+
+/// Schema of an instance.
+#[derive(Debug, Clone, Serialize)]
+pub struct InstanceInfo {
+    pub id: usize,
+
+    /// Index of the deploy configuration
+    pub index: usize,
+
+    pub ssserver: ProcInfo,
+
+    pub kcptun: Option<ProcInfo>,
+}
+
+/// Schema of a process of instance.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcInfo {
+    /// `running` or `exited`
+    pub state: &'static str,
+
+    pub pid: Option<u32>,
+
+    /// Exit code of the last exit
+    pub exit_code: Option<i32>,
+
+    /// Uptime in seconds
+    pub uptime: Option<u64>,
+
+    pub restarts: u32,
+
+    /// Resident set size in bytes
+    pub rss: Option<u64>,
+
+    /// Cpu time in seconds
+    pub cpu: Option<f64>,
+
+    pub ports: Vec<PortInfo>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortInfo {
+    pub protocol: &'static str,
+
+    pub port: u16,
+
+    /// The port is bound by the process
+    pub bound: bool,
+}
+
+/// Schema of a deploy configuration.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigInfo {
+    pub index: usize,
+
+    #[serde(flatten)]
+    pub config: DeployConfig,
+}
+
+impl InstanceInfo {
+    pub fn new(inst: &SsInstance) -> Self {
+        Self {
+            id: inst.id,
+            index: inst.index,
+            ssserver: ProcInfo::new(&inst.supervisor.ss()),
+            kcptun: inst.supervisor.kcp().as_ref().map(ProcInfo::new),
+        }
+    }
+}
+
+impl ProcInfo {
+    pub fn new(state: &ProcState) -> Self {
+        let usage = state.pid.and_then(usage);
+        let bound = state.pid.map(bound_ports).unwrap_or_default();
+
+        Self {
+            state: if state.pid.is_some() {
+                "running"
+            } else {
+                "exited"
+            },
+            pid: state.pid,
+            exit_code: state.exited.and_then(|v| v.status).and_then(|v| v.code()),
+            uptime: state.pid.map(|_| state.uptime().as_secs()),
+            restarts: state.restarts,
+            rss: usage.map(|v| v.rss),
+            cpu: usage.map(|v| v.cpu.as_secs_f64()),
+            ports: state
+                .ports
+                .iter()
+                .map(|v| {
+                    let (protocol, port) = match v {
+                        Listen::Tcp(port) => ("tcp", *port),
+                        Listen::Udp(port) => ("udp", *port),
+                    };
+
+                    PortInfo {
+                        protocol,
+                        port,
+                        bound: bound.contains(v),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ConfigInfo {
+    pub fn new(index: usize, config: &DeployConfig, show_secrets: bool) -> Self {
+        Self {
+            index,
+            config: if show_secrets {
+                config.clone()
+            } else {
+                config.redacted()
+            },
+        }
+    }
+}
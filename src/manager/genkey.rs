@@ -0,0 +1,79 @@
+use std::io::Write;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use cote::prelude::*;
+
+use crate::config::Method;
+
+use super::AppContext;
+
+#[derive(Debug, Cote)]
+#[cote(shellcomp, aborthelp, width = 50, overload, notexit)]
+pub struct GenKey {
+    /// Generate the key for given method, default is the method of configuration
+    #[arg(alias = "-m", scvalues = Method::values())]
+    pub method: Option<Method>,
+
+    /// Generate the key of kcptun too
+    #[arg(alias = "-k")]
+    pub kcp: bool,
+
+    /// Rotate the keys of the loaded configuration with given index
+    #[arg(alias = "-i")]
+    pub index: Option<usize>,
+}
+
+impl GenKey {
+    pub async fn invoke_cmd(&self, ac: &mut AppContext) -> color_eyre::Result<()> {
+        let Some(index) = self.index else {
+            let method = self.method.unwrap_or_default();
+
+            writeln!(ac.out, "{method}: {}", gen_key(&method)?)?;
+            if self.kcp {
+                writeln!(ac.out, "kcptun: {}", gen_key(&Method::None)?)?;
+            }
+            return Ok(());
+        };
+        let deploy_cfg = ac.cfgs.get_mut(index).ok_or_else(|| {
+            color_eyre::Report::msg(
+                "Index out of bound, load the configurations using command `load`",
+            )
+        })?;
+        // check the kcptun configuration before changing anything
+        if self.kcp && deploy_cfg.kcp_cfg.is_none() {
+            return Err(color_eyre::Report::msg(format!(
+                "No kcptun configuration at index {index}"
+            )));
+        }
+        let method = self.method.unwrap_or(deploy_cfg.ss_cfg.method);
+        let password = gen_key(&method)?;
+        let key = if self.kcp {
+            Some(gen_key(&Method::None)?)
+        } else {
+            None
+        };
+
+        deploy_cfg.ss_cfg.method = method;
+        deploy_cfg.ss_cfg.password = password.clone().into();
+        writeln!(ac.out, "{method}: {password}")?;
+        if let (Some(kcp_cfg), Some(key)) = (deploy_cfg.kcp_cfg.as_mut(), key) {
+            kcp_cfg.key = key.clone().into();
+            writeln!(ac.out, "kcptun: {key}")?;
+        }
+        writeln!(
+            ac.out,
+            "Keys of configuration {index} rotated, restart the instances to apply them"
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Generate a random base64 key with the length required by `method`.
+pub fn gen_key(method: &Method) -> color_eyre::Result<String> {
+    let mut key = vec![0u8; method.key_len().unwrap_or(32)];
+
+    getrandom::fill(&mut key).map_err(|e| color_eyre::Report::msg(format!("{e}")))?;
+    Ok(STANDARD.encode(key))
+}
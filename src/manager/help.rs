@@ -1,22 +1,49 @@
 use cote::prelude::*;
 
-use super::{kill::Kill, list::List, load::Load, start::Start, AppContext};
+use super::{
+    add::Add, check::Check, clone::CloneConfig, export::ExportClient, genkey::GenKey,
+    import::Import, kill::Kill, list::List, load::Load, logs::Logs, remove::Remove, save::Save,
+    set::SetField, start::Start, status::Status, AppContext,
+};
 
 #[derive(Debug, Cote)]
 #[cote(shellcomp, aborthelp, width = 50, overload, notexit)]
 pub struct Help {
     /// Show help message of given command
-    #[pos(scvalues = ["kill", "list", "load", "start"])]
+    #[pos(scvalues = ["add", "check", "clone", "export-client", "genkey", "import", "kill", "list", "load", "logs", "remove", "save", "set", "start", "status"])]
     name: String,
 }
 
 impl Help {
     pub async fn invoke_cmd(&self, _ac: &mut AppContext) -> color_eyre::Result<()> {
         let cmds = [
+            ("add", Add::into_parser()?, Add::new_help_context()),
+            ("check", Check::into_parser()?, Check::new_help_context()),
+            (
+                "clone",
+                CloneConfig::into_parser()?,
+                CloneConfig::new_help_context(),
+            ),
+            (
+                "export-client",
+                ExportClient::into_parser()?,
+                ExportClient::new_help_context(),
+            ),
+            ("genkey", GenKey::into_parser()?, GenKey::new_help_context()),
+            ("import", Import::into_parser()?, Import::new_help_context()),
             ("kill", Kill::into_parser()?, Kill::new_help_context()),
             ("list", List::into_parser()?, List::new_help_context()),
             ("load", Load::into_parser()?, Load::new_help_context()),
+            ("logs", Logs::into_parser()?, Logs::new_help_context()),
+            ("remove", Remove::into_parser()?, Remove::new_help_context()),
+            ("save", Save::into_parser()?, Save::new_help_context()),
+            (
+                "set",
+                SetField::into_parser()?,
+                SetField::new_help_context(),
+            ),
             ("start", Start::into_parser()?, Start::new_help_context()),
+            ("status", Status::into_parser()?, Status::new_help_context()),
         ];
 
         for (name, parser, help_ctx) in &cmds {
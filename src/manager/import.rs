@@ -0,0 +1,517 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use color_eyre::eyre::eyre;
+use cote::prelude::*;
+use serde_json::Value;
+use tokio::fs::read_to_string;
+
+use crate::config::default_kcp_port;
+use crate::config::Crypt;
+use crate::config::DeployConfig;
+use crate::config::FileFormat;
+use crate::config::KcpConfig;
+use crate::config::KcpMode;
+use crate::config::Method;
+use crate::config::SsConfig;
+use crate::validate::validate;
+
+use super::AppContext;
+
+pub const DEFAULT_BIN: &str = "~/.cargo/bin/ssserver";
+
+pub const DEFAULT_KCP: &str = "~/.cargo/bin/kcptun";
+
+#[derive(Debug, Cote)]
+#[cote(shellcomp, aborthelp, width = 50, overload, notexit)]
+pub struct Import {
+    /// Import the server from ss:// URI
+    #[arg(alias = "-u")]
+    pub uri: Option<String>,
+
+    /// Import the servers from configuration of shadowsocks-rust, JSON, TOML or YAML
+    #[arg(alias = "-c")]
+    pub config: Option<String>,
+
+    /// Import the server configuration of kcptun
+    #[arg(alias = "-k")]
+    pub kcp_config: Option<String>,
+
+    /// Set the path of ssserver of imported configurations
+    pub bin: Option<PathBuf>,
+
+    /// Set the path of kcptun of imported configurations
+    pub kcp: Option<PathBuf>,
+}
+
+impl Import {
+    pub async fn invoke_cmd(&self, ac: &mut AppContext) -> color_eyre::Result<()> {
+        if self.uri.is_none() && self.config.is_none() && self.kcp_config.is_none() {
+            return Err(eyre!(
+                "Nothing to import, use --uri, --config or --kcp-config"
+            ));
+        }
+
+        let mut servers = vec![];
+
+        if let Some(uri) = &self.uri {
+            servers.push(parse_uri(uri)?);
+        }
+        if let Some(path) = &self.config {
+            let path = ac.path(path)?.display().to_string();
+            let path = shellexpand::full(&path)?;
+            let config: Value =
+                FileFormat::from_path(&*path).parse(&read_to_string(&*path).await?)?;
+
+            match config.get("servers").and_then(Value::as_array) {
+                Some(array) => {
+                    for server in array {
+                        servers.push(parse_ss_config(&inherit_top(&config, server))?);
+                    }
+                }
+                None => servers.push(parse_ss_config(&config)?),
+            }
+        }
+
+        let bin = match &self.bin {
+            Some(bin) => ac.bin_path(bin)?,
+            None => PathBuf::from(DEFAULT_BIN),
+        };
+        let kcp = match &self.kcp {
+            Some(kcp) => Some(ac.bin_path(kcp)?),
+            None => None,
+        };
+        let mut cfgs: Vec<_> = servers
+            .into_iter()
+            .map(|(ss_cfg, kcp_cfg)| DeployConfig {
+                bin: bin.clone(),
+                kcp: kcp.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_KCP)),
+                ss_cfg,
+                kcp_cfg,
+                ..Default::default()
+            })
+            .collect();
+
+        if let Some(path) = &self.kcp_config {
+            let path = ac.path(path)?.display().to_string();
+            let path = shellexpand::full(&path)?;
+            let config: Value =
+                FileFormat::from_path(&*path).parse(&read_to_string(&*path).await?)?;
+            let (target_port, listen_port, kcp_cfg) = parse_kcp_config(&config)?;
+            // attach to the imported servers, or the loaded one listen on the target port
+            let found = if cfgs.is_empty() {
+                ac.cfgs
+                    .iter_mut()
+                    .enumerate()
+                    .filter(|(_, v)| v.ss_cfg.server_port == target_port)
+                    .collect::<Vec<_>>()
+            } else {
+                let start = ac.cfgs.len();
+
+                cfgs.iter_mut()
+                    .enumerate()
+                    .map(|(i, v)| (start + i, v))
+                    .collect()
+            };
+
+            if found.is_empty() {
+                return Err(eyre!(
+                    "No configuration listen on the target port {target_port} of kcptun"
+                ));
+            }
+            for (index, cfg) in found {
+                let port = cfg.ss_cfg.server_port;
+
+                if let Some(listen_port) =
+                    listen_port.filter(|v| default_kcp_port(port) != Some(*v))
+                {
+                    writeln!(
+                        ac.out,
+                        "kcptun of configuration {index} listen on port {listen_port}, use `start -l {listen_port}` instead of the default port {}",
+                        u64::from(port) + 1
+                    )?;
+                }
+                cfg.kcp_cfg = Some(kcp_cfg.clone());
+                if let Some(kcp) = &kcp {
+                    cfg.kcp = kcp.clone();
+                }
+                writeln!(
+                    ac.out,
+                    "Attached kcptun configuration to configuration {index}"
+                )?;
+            }
+        }
+
+        let start = ac.cfgs.len();
+
+        for (offset, cfg) in cfgs.iter().enumerate() {
+            let kcp = if cfg.kcp_cfg.is_some() {
+                ", kcptun"
+            } else {
+                ""
+            };
+
+            writeln!(
+                ac.out,
+                "Imported configuration {}: port {}, {}{kcp}",
+                start + offset,
+                cfg.ss_cfg.server_port,
+                cfg.ss_cfg.method
+            )?;
+        }
+        ac.cfgs.extend(cfgs);
+        for issue in validate(&ac.cfgs) {
+            // only display the issues of imported configurations
+            if (start..ac.cfgs.len()).any(|v| issue.path.starts_with(&format!("$[{v}]"))) {
+                writeln!(ac.out, "{issue}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse the SIP002 URI `ss://userinfo@host:port/?plugin=...#tag`,
+/// or the legacy one `ss://base64(method:password@host:port)#tag`.
+pub fn parse_uri(uri: &str) -> color_eyre::Result<(SsConfig, Option<KcpConfig>)> {
+    let rest = uri
+        .strip_prefix("ss://")
+        .ok_or_else(|| eyre!("Not a ss:// URI: {uri}"))?;
+    let rest = rest.split_once('#').map(|v| v.0).unwrap_or(rest);
+    let (rest, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let rest = rest.trim_end_matches('/');
+    let (userinfo, address) = match rest.rsplit_once('@') {
+        Some((userinfo, address)) => {
+            // userinfo is percent encoded if it has ':'
+            let userinfo = if userinfo.contains(':') || userinfo.contains('%') {
+                urlencoding::decode(userinfo)?.into_owned()
+            } else {
+                decode_base64(userinfo)?
+            };
+
+            (userinfo, address.to_string())
+        }
+        None => {
+            let decoded = decode_base64(rest)?;
+            let (userinfo, address) = decoded
+                .rsplit_once('@')
+                .ok_or_else(|| eyre!("Invalid ss:// URI: {uri}"))?;
+
+            (userinfo.to_string(), address.to_string())
+        }
+    };
+    let (method, password) = userinfo
+        .split_once(':')
+        .ok_or_else(|| eyre!("Invalid userinfo of ss:// URI: {uri}"))?;
+    let (_, port) = address
+        .rsplit_once(':')
+        .ok_or_else(|| eyre!("Missing port in ss:// URI: {uri}"))?;
+    let mut server_port: u32 = port.parse()?;
+    let mut kcp_cfg = None;
+
+    for param in query.split('&') {
+        if let Some(plugin) = param.strip_prefix("plugin=") {
+            let plugin = urlencoding::decode(plugin)?;
+            // the plugin name is followed by its options
+            let mut opts = plugin_opts(&plugin);
+
+            if opts.first().is_some_and(|v| v.0 == "kcptun") {
+                opts.remove(0);
+                kcp_cfg = Some(kcp_config_from(opts)?);
+                // the port of URI is kcptun listen port, which is {port + 1}
+                server_port = server_port.saturating_sub(1);
+            }
+        }
+    }
+
+    Ok((
+        SsConfig {
+            server: String::from("0.0.0.0"),
+            server_port,
+            password: password.into(),
+            timeout: 300,
+            method: Method::try_from(method)?,
+            fast_open: false,
+        },
+        kcp_cfg,
+    ))
+}
+
+/// The servers inherit the top level fields of configuration, such as `method` and `timeout`.
+fn inherit_top(config: &Value, server: &Value) -> Value {
+    let mut merged = config.clone();
+
+    if let (Some(merged), Some(server)) = (merged.as_object_mut(), server.as_object()) {
+        merged.remove("servers");
+        merged.extend(server.clone());
+    }
+    merged
+}
+
+/// Parse the server in configuration of shadowsocks-rust.
+pub fn parse_ss_config(value: &Value) -> color_eyre::Result<(SsConfig, Option<KcpConfig>)> {
+    let str_of = |name: &str| value.get(name).and_then(Value::as_str);
+    let method = str_of("method").ok_or_else(|| eyre!("Missing `method` in configuration"))?;
+    let server_port = value
+        .get("server_port")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| eyre!("Missing `server_port` in configuration"))?;
+    let server_port =
+        u32::try_from(server_port).map_err(|_| eyre!("Invalid `server_port` {server_port}"))?;
+    let timeout = value.get("timeout").and_then(Value::as_u64).unwrap_or(300);
+    let timeout = u32::try_from(timeout).map_err(|_| eyre!("Invalid `timeout` {timeout}"))?;
+    let mut kcp_cfg = None;
+
+    if str_of("plugin").is_some_and(|v| v.contains("kcptun")) {
+        let opts = plugin_opts(str_of("plugin_opts").unwrap_or_default());
+
+        kcp_cfg = Some(kcp_config_from(opts)?);
+    }
+
+    Ok((
+        SsConfig {
+            server: str_of("server").unwrap_or("0.0.0.0").to_string(),
+            server_port,
+            password: str_of("password").unwrap_or_default().into(),
+            timeout,
+            method: Method::try_from(method)?,
+            fast_open: value
+                .get("fast_open")
+                .and_then(Value::as_bool)
+                .unwrap_or_default(),
+        },
+        kcp_cfg,
+    ))
+}
+
+/// Parse the `server.json` of kcptun, return the target port, listen port and configuration.
+pub fn parse_kcp_config(value: &Value) -> color_eyre::Result<(u32, Option<u32>, KcpConfig)> {
+    let port_of = |addr: &str| addr.rsplit_once(':').and_then(|v| v.1.parse::<u32>().ok());
+    let target = value
+        .get("target")
+        .and_then(Value::as_str)
+        .ok_or_else(|| eyre!("Missing `target` in kcptun configuration"))?;
+    let target_port = port_of(target).ok_or_else(|| eyre!("Invalid target `{target}`"))?;
+    let listen_port = value
+        .get("listen")
+        .and_then(Value::as_str)
+        .and_then(port_of);
+    let opts = value
+        .as_object()
+        .map(|v| {
+            v.iter()
+                .filter_map(|(key, val)| match val {
+                    Value::String(val) => Some((key.clone(), val.clone())),
+                    Value::Number(_) | Value::Bool(_) => Some((key.clone(), val.to_string())),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let mut kcp_cfg = kcp_config_from(opts)?;
+
+    if let Some((host, _)) = target.rsplit_once(':') {
+        if !host.is_empty() {
+            kcp_cfg.server = host.trim_matches(['[', ']']).to_string();
+        }
+    }
+    Ok((target_port, listen_port, kcp_cfg))
+}
+
+/// Create the configuration from options of kcptun, the missing options are kcptun defaults.
+fn kcp_config_from(opts: Vec<(String, String)>) -> color_eyre::Result<KcpConfig> {
+    let mut cfg = KcpConfig::kcptun_default();
+
+    for (key, val) in opts {
+        match key.as_str() {
+            "crypt" => cfg.crypt = Crypt::try_from(val.as_str())?,
+            "key" => cfg.key = val.into(),
+            "mode" => cfg.mode = KcpMode::try_from(val.as_str())?,
+            "mtu" => cfg.mtu = val.parse()?,
+            "sndwnd" => cfg.send_wnd = val.parse()?,
+            "rcvwnd" => cfg.recv_wnd = val.parse()?,
+            "datashard" | "ds" => cfg.data_shard = val.parse()?,
+            "parityshard" | "ps" => cfg.parity_shard = val.parse()?,
+            "dscp" => cfg.dscp = val.parse()?,
+            "nocomp" => cfg.comp = !val.parse::<bool>()?,
+            _ => {}
+        }
+    }
+    Ok(cfg)
+}
+
+fn decode_base64(val: &str) -> color_eyre::Result<String> {
+    let val = val.trim_end_matches('=');
+    let decoded = URL_SAFE_NO_PAD
+        .decode(val)
+        .or_else(|_| STANDARD_NO_PAD.decode(val))?;
+
+    Ok(String::from_utf8(decoded)?)
+}
+
+/// Split the SIP003 plugin options `k=v;k=v`, the `\\` escapes the next character,
+/// the value of option without `=` is `true`.
+fn plugin_opts(opts: &str) -> Vec<(String, String)> {
+    let mut rets = vec![];
+    let mut key = String::new();
+    let mut value = None;
+    let mut chars = opts.chars();
+
+    loop {
+        let char = chars.next();
+
+        match char {
+            Some(';') | None => {
+                let value = value.take().unwrap_or_else(|| String::from("true"));
+
+                rets.push((std::mem::take(&mut key), value));
+                if char.is_none() {
+                    break rets;
+                }
+            }
+            Some('=') if value.is_none() => value = Some(String::new()),
+            Some(char) => {
+                let char = if char == '\\' {
+                    chars.next()
+                } else {
+                    Some(char)
+                };
+
+                value.as_mut().unwrap_or(&mut key).extend(char);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::manager::export::plugin_opts as export_opts;
+    use crate::manager::export::uri;
+
+    use super::*;
+
+    #[test]
+    fn split_plugin_opts() {
+        let opts = |v: &[(&str, &str)]| {
+            v.iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<Vec<_>>()
+        };
+        let table = [
+            ("kcptun", opts(&[("kcptun", "true")])),
+            (
+                "crypt=aes;nocomp",
+                opts(&[("crypt", "aes"), ("nocomp", "true")]),
+            ),
+            (r"key=a\;b\=c", opts(&[("key", "a;b=c")])),
+            (r"key=a=b\\", opts(&[("key", "a=b\\")])),
+        ];
+
+        for (val, expected) in table {
+            assert_eq!(plugin_opts(val), expected, "{val}");
+        }
+    }
+
+    #[test]
+    fn parse_uri_userinfo() {
+        // base64 userinfo, percent encoded userinfo of 2022 methods and the legacy format
+        let table = [
+            (
+                "ss://YWVzLTI1Ni1nY206cGFzcw@example.com:8388/#name",
+                Method::Aes256,
+                "pass",
+            ),
+            (
+                "ss://2022-blake3-aes-128-gcm:a%2Bb%2Fc%3D%3D@[::1]:8388",
+                Method::Blake3Aes128_2022,
+                "a+b/c==",
+            ),
+            (
+                "ss://YWVzLTI1Ni1nY206cGFzc0BleGFtcGxlLmNvbTo4Mzg4",
+                Method::Aes256,
+                "pass",
+            ),
+        ];
+
+        for (uri, method, password) in table {
+            let (ss_cfg, kcp_cfg) = parse_uri(uri).unwrap();
+
+            assert_eq!(ss_cfg.method, method, "{uri}");
+            assert_eq!(ss_cfg.password, password.into(), "{uri}");
+            assert_eq!(ss_cfg.server_port, 8388, "{uri}");
+            assert!(kcp_cfg.is_none(), "{uri}");
+        }
+        assert!(parse_uri("http://example.com").is_err());
+        assert!(parse_uri("ss://YWVzLTI1Ni1nY206cGFzcw@example.com").is_err());
+    }
+
+    #[test]
+    fn servers_inherit_top_level() {
+        let config = serde_json::json!({
+            "method": "aes-256-gcm",
+            "timeout": 600,
+            "servers": [
+                { "server": "::", "server_port": 8388, "password": "a" },
+                { "server_port": 8390, "password": "b", "method": "aes-128-gcm" },
+            ]
+        });
+        let servers: Vec<_> = config["servers"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| parse_ss_config(&inherit_top(&config, v)).unwrap().0)
+            .collect();
+
+        assert_eq!(servers[0].method, Method::Aes256);
+        assert_eq!(servers[0].timeout, 600);
+        assert_eq!(servers[0].server, "::");
+        assert_eq!(servers[1].method, Method::Aes128);
+        assert_eq!(servers[1].timeout, 600);
+
+        let config = serde_json::json!({ "method": "aes-256-gcm", "server_port": 4294967296u64 });
+
+        assert!(parse_ss_config(&config).is_err());
+    }
+
+    #[test]
+    fn round_trip_export_uri() {
+        let mut cfg = DeployConfig::default();
+        let kcp_cfg = KcpConfig {
+            crypt: Crypt::Salsa20,
+            key: "k;e=y".into(),
+            mode: KcpMode::Fast3,
+            comp: false,
+            ..KcpConfig::kcptun_default()
+        };
+
+        cfg.ss_cfg.method = Method::Blake3Aes128_2022;
+        cfg.ss_cfg.password = "a+b/c==".into();
+        cfg.ss_cfg.server_port = 8388;
+        for kcp in [None, Some(&kcp_cfg)] {
+            // the port of URI is kcptun listen port if kcptun enabled
+            let port = if kcp.is_some() { 8389 } else { 8388 };
+            let opts = kcp.map(export_opts);
+            let uri = uri(
+                &cfg.ss_cfg,
+                "a+b/c==",
+                "example.com",
+                port,
+                opts.as_deref(),
+                "name",
+            );
+            let (ss_cfg, imported) = parse_uri(&uri).unwrap();
+
+            assert_eq!(ss_cfg.method, cfg.ss_cfg.method, "{uri}");
+            assert_eq!(ss_cfg.password, cfg.ss_cfg.password, "{uri}");
+            assert_eq!(ss_cfg.server_port, 8388, "{uri}");
+            assert_eq!(
+                imported.map(|v| serde_json::to_value(v).unwrap()),
+                kcp.map(|v| serde_json::to_value(v).unwrap()),
+                "{uri}"
+            );
+        }
+    }
+}
@@ -1,37 +1,75 @@
+use std::io::Write;
+use std::time::Duration;
+
 use color_eyre::eyre::eyre;
 use cote::prelude::*;
+use tokio::task::JoinSet;
 
 use super::AppContext;
 
+pub const DEFAULT_KILL_TIMEOUT: u64 = 5;
+
 #[derive(Debug, Cote)]
 #[cote(shellcomp, aborthelp, width = 50, overload, notexit)]
 pub struct Kill {
     /// Kill all shadowsocks instance
     pub all: bool,
 
-    /// Kill given shadowsocks instance
+    /// Kill the instance with given instance id
     #[arg(alias = "-i", value = 0usize)]
     pub id: Option<usize>,
+
+    /// Seconds to wait after SIGTERM before sending SIGKILL
+    #[arg(alias = "-t", value = DEFAULT_KILL_TIMEOUT)]
+    pub timeout: Option<u64>,
 }
 
 impl Kill {
     pub async fn invoke_cmd(&self, ctx: &mut AppContext) -> color_eyre::Result<()> {
-        for inst in ctx
-            .insts
-            .iter_mut()
-            .filter(|v| self.all || Some(v.id) == self.id)
-        {
-            inst.ss.kill().await?;
-            if let Some(kcp) = inst.kcp.as_mut() {
-                kcp.kill().await?;
-            }
-        }
-        if self.all {
-            ctx.insts.clear();
+        let insts = if self.all {
+            std::mem::take(&mut ctx.insts)
         } else if let Some(index) = ctx.insts.iter().position(|v| Some(v.id) == self.id) {
-            ctx.insts.remove(index);
+            vec![ctx.insts.remove(index)]
         } else if let Some(index) = self.id {
             return Err(eyre!("Invalid id `{index}`, no instance found"));
+        } else {
+            vec![]
+        };
+
+        let timeout = Duration::from_secs(self.timeout.unwrap_or_default());
+        let mut tasks = JoinSet::new();
+        let mut reports = vec![];
+        let mut errors = vec![];
+
+        // stop the instances concurrently, each of them may wait the timeout
+        for mut inst in insts {
+            tasks.spawn(async move {
+                let ret = inst.supervisor.stop(timeout).await;
+
+                (inst, ret)
+            });
+        }
+        while let Some(ret) = tasks.join_next().await {
+            match ret {
+                Ok((inst, Ok(report))) => reports.push((inst.id, report)),
+                Ok((inst, Err(e))) => {
+                    errors.push(format!("Failed stop instance {}: {e}", inst.id));
+                    // keep tracking the instance, kill it again later
+                    ctx.insts.push(inst);
+                }
+                Err(e) => errors.push(format!("Failed stop instance: {e}")),
+            }
+        }
+        ctx.insts.sort_by_key(|v| v.id);
+        reports.sort_by_key(|v| v.0);
+        for (id, report) in reports {
+            writeln!(ctx.out, "Instance {id}: ssserver {}", report.ss)?;
+            if let Some(kcp) = report.kcp {
+                writeln!(ctx.out, "Instance {id}: kcptun {kcp}")?;
+            }
+        }
+        if !errors.is_empty() {
+            return Err(eyre!(errors.join("\n")));
         }
 
         Ok(())
@@ -1,6 +1,14 @@
+use std::io::Write;
+
 use cote::prelude::*;
 use prettytable::{Row, Table};
 
+use crate::supervisor::Exited;
+use crate::supervisor::ProcState;
+
+use super::format::ConfigInfo;
+use super::format::Format;
+use super::format::InstanceInfo;
 use super::AppContext;
 
 #[derive(Debug, Cote)]
@@ -9,31 +17,79 @@ pub struct List {
     /// Instead, list the configuration
     #[arg(alias = "-l")]
     pub local: bool,
+
+    /// Set the output format: table, json or yaml
+    #[arg(alias = "-f", scvalues = Format::values())]
+    pub format: Option<Format>,
+
+    /// Display the password and key of configurations
+    pub show_secrets: bool,
 }
 
 impl List {
     pub async fn invoke_cmd(&self, ac: &mut AppContext) -> color_eyre::Result<()> {
+        let format = self.format.unwrap_or_default();
+
         if self.local {
-            println!("-------------------CONFIG------------------------");
-            for (index, cfg) in ac.cfgs.iter().enumerate() {
-                println!("INDEX: {index}");
-                println!("{}", serde_json::to_string_pretty(cfg)?);
-                println!("-----------------------------------------------");
+            let cfgs: Vec<_> = ac
+                .cfgs
+                .iter()
+                .enumerate()
+                .map(|(index, cfg)| ConfigInfo::new(index, cfg, self.show_secrets))
+                .collect();
+
+            if format != Format::Table {
+                return format.write(&mut ac.out, &cfgs);
             }
+            writeln!(ac.out, "-------------------CONFIG------------------------")?;
+            for ConfigInfo { index, config } in cfgs.iter() {
+                writeln!(ac.out, "INDEX: {index}")?;
+                writeln!(ac.out, "{}", serde_json::to_string_pretty(config)?)?;
+                writeln!(ac.out, "-----------------------------------------------")?;
+            }
+        } else if format != Format::Table {
+            let insts: Vec<_> = ac.insts.iter().map(InstanceInfo::new).collect();
+
+            format.write(&mut ac.out, &insts)?;
         } else {
             let mut table = Table::new();
 
-            table.add_row(Row::from(["Config", "Shadowsock", "Kcptun"]));
+            table.add_row(Row::from([
+                "Id",
+                "Config",
+                "Shadowsock",
+                "Kcptun",
+                "Restarts",
+            ]));
             for inst in ac.insts.iter() {
+                let ss = inst.supervisor.ss();
+                let kcp = inst.supervisor.kcp();
+
                 table.add_row(Row::from(vec![
                     inst.id.to_string(),
-                    inst.ss.id().map(|v| v.to_string()).unwrap_or_default(),
-                    format!("{:?}", inst.kcp.as_ref().map(|v| v.id())),
+                    inst.index.to_string(),
+                    display_proc(&ss),
+                    kcp.as_ref().map(display_proc).unwrap_or_default(),
+                    (ss.restarts + kcp.map(|v| v.restarts).unwrap_or_default()).to_string(),
                 ]));
             }
-            table.printstd();
+            table.print(&mut ac.out)?;
         }
 
         Ok(())
     }
 }
+
+fn display_proc(state: &ProcState) -> String {
+    match (state.pid, &state.exited) {
+        (Some(pid), _) => pid.to_string(),
+        (
+            None,
+            Some(Exited {
+                status: Some(status),
+                ..
+            }),
+        ) => format!("exited({status})"),
+        (None, _) => String::from("exited"),
+    }
+}
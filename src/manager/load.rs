@@ -1,6 +1,15 @@
+use std::io::Write;
+
 use cote::prelude::*;
 use tokio::fs::read_to_string;
 
+use crate::config::ConfigFile;
+use crate::config::DeployConfig;
+use crate::config::FileFormat;
+use crate::inherit::Inherit;
+use crate::validate::validate;
+
+use super::check::count_errors;
 use super::AppContext;
 
 pub const DEFAULT_CONFIG: &str = "~/shadowsocks.json";
@@ -11,15 +20,46 @@ pub struct Load {
     /// Set the path of configuration
     #[arg(alias = "-c", value = DEFAULT_CONFIG)]
     pub config: Option<String>,
+
+    /// Load the configurations even if they are invalid
+    pub force: bool,
 }
 
 impl Load {
     pub async fn invoke_cmd(&self, ac: &mut AppContext) -> color_eyre::Result<()> {
-        let path = self.config.as_ref().unwrap();
-        let path = shellexpand::full(&path)?;
+        let path = &ac
+            .path(self.config.as_ref().unwrap())?
+            .display()
+            .to_string();
+        let (cfgs, inherit) = read_configs(path).await?;
+        let issues = validate(&cfgs);
+
+        for issue in issues.iter() {
+            writeln!(ac.out, "{issue}")?;
+        }
 
-        ac.cfgs = serde_json::from_str(&read_to_string(&*path).await?)?;
+        let errors = count_errors(&issues);
+
+        if errors > 0 && !self.force {
+            return Err(color_eyre::Report::msg(format!(
+                "Refuse to load `{path}` with {errors} error(s), use `--force` to load anyway"
+            )));
+        }
+        ac.cfgs = cfgs;
+        ac.inherit = inherit;
+        ac.path = Some(path.to_string());
 
         Ok(())
     }
 }
+
+/// Read the deploy configurations from `path`, the format is decided by extension.
+///
+/// Return the resolved configurations and the defaults and profiles they extend.
+pub async fn read_configs(path: &str) -> color_eyre::Result<(Vec<DeployConfig>, Inherit)> {
+    let path = shellexpand::full(path)?;
+    let content = read_to_string(&*path).await?;
+    let file = ConfigFile::from_value(FileFormat::from_path(&*path).parse(&content)?)?;
+
+    Ok((file.resolve()?, file.inherit))
+}
@@ -0,0 +1,171 @@
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use color_eyre::eyre::eyre;
+use cote::prelude::*;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncSeekExt;
+use tokio::select;
+use tokio::time::sleep;
+
+use crate::capture::Ring;
+use crate::supervisor::ProcSpec;
+
+use super::cancelled;
+use super::AppContext;
+use super::Follow;
+
+pub const DEFAULT_LINES: usize = 10;
+
+/// Bytes read from the end of log file to find the last lines.
+const TAIL_SIZE: u64 = 1024 * 1024;
+
+#[derive(Debug, Cote)]
+#[cote(shellcomp, aborthelp, width = 50, overload, notexit)]
+pub struct Logs {
+    /// Show the output of the instance with given instance id
+    #[arg(alias = "-i")]
+    pub id: usize,
+
+    /// Show the output of kcptun instead of ssserver
+    #[arg(alias = "-k")]
+    pub kcp: bool,
+
+    /// Show the stderr instead of stdout
+    #[arg(alias = "-e")]
+    pub err: bool,
+
+    /// Set the number of last lines
+    #[arg(alias = "-n", value = DEFAULT_LINES)]
+    pub lines: Option<usize>,
+
+    /// Keep printing the new output until enter or ctrl-c pressed
+    #[arg(alias = "-f")]
+    pub follow: bool,
+}
+
+impl Logs {
+    pub async fn invoke_cmd(&self, ac: &mut AppContext) -> color_eyre::Result<()> {
+        let source = self.source(ac)?;
+        let end = source.end().await?;
+        let (data, mut offset) = source.since(end.saturating_sub(TAIL_SIZE)).await?;
+
+        ac.out
+            .write_all(last_lines(&data, self.lines.unwrap_or(DEFAULT_LINES)))?;
+        ac.out.flush()?;
+        if !self.follow {
+            return Ok(());
+        }
+        let mut out = ac.out.clone();
+        let mut cancel = ac.cancel.take();
+
+        ac.follow = Some(Follow::new(async move {
+            loop {
+                select! {
+                    _ = sleep(Duration::from_millis(500)) => { }
+                    _ = cancelled(&mut cancel) => break,
+                }
+
+                let (data, end) = source.since(offset).await?;
+
+                out.write_all(&data)?;
+                out.flush()?;
+                offset = end;
+            }
+            Ok(())
+        }));
+        Ok(())
+    }
+
+    fn source(&self, ac: &AppContext) -> color_eyre::Result<Source> {
+        let id = self.id;
+        let inst = ac
+            .insts
+            .iter()
+            .find(|v| v.id == id)
+            .ok_or_else(|| eyre!("Instance {id} not found"))?;
+        let (name, spec) = if self.kcp {
+            let spec = inst.supervisor.kcp_spec();
+
+            (
+                "kcptun",
+                spec.ok_or_else(|| eyre!("Instance {id} has no kcptun"))?,
+            )
+        } else {
+            ("ssserver", inst.supervisor.ss_spec())
+        };
+
+        Source::of(spec, self.err).ok_or_else(|| {
+            eyre!("Output of {name} is not captured, set the log path or start it by the daemon")
+        })
+    }
+}
+
+/// Where the output of process is written.
+pub enum Source {
+    File(PathBuf),
+
+    Ring(Ring),
+}
+
+impl Source {
+    /// The stderr if `err` is true, or the stdout of process, `None` if it is not captured.
+    pub fn of(spec: &ProcSpec, err: bool) -> Option<Self> {
+        let path = if err { &spec.stderr } else { &spec.stdout };
+
+        match (path, &spec.capture) {
+            (Some(path), _) => Some(Source::File(path.clone())),
+            (None, Some(capture)) if err => Some(Source::Ring(capture.err.clone())),
+            (None, Some(capture)) => Some(Source::Ring(capture.out.clone())),
+            (None, None) => None,
+        }
+    }
+
+    /// Offset of the end of output.
+    pub async fn end(&self) -> std::io::Result<u64> {
+        match self {
+            Source::File(path) => Ok(tokio::fs::metadata(path).await?.len()),
+            Source::Ring(ring) => Ok(ring.since(u64::MAX).1),
+        }
+    }
+
+    /// Return the output after `offset`, and the offset of the end.
+    pub async fn since(&self, offset: u64) -> std::io::Result<(Vec<u8>, u64)> {
+        match self {
+            Source::File(path) => {
+                let mut file = tokio::fs::File::open(path).await?;
+                let len = file.metadata().await?.len();
+                // the file is rotated or truncated
+                let offset = if len < offset { 0 } else { offset };
+                let mut data = vec![];
+
+                file.seek(SeekFrom::Start(offset)).await?;
+                let read = file.take(len - offset).read_to_end(&mut data).await?;
+
+                Ok((data, offset + read as u64))
+            }
+            Source::Ring(ring) => Ok(ring.since(offset)),
+        }
+    }
+}
+
+/// Return the last `n` lines of `data`.
+pub fn last_lines(data: &[u8], n: usize) -> &[u8] {
+    if n == 0 {
+        return &[];
+    }
+    let body = data.strip_suffix(b"\n").unwrap_or(data);
+
+    match body
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, v)| **v == b'\n')
+        .nth(n - 1)
+    {
+        Some((i, _)) => &data[i + 1..],
+        None => data,
+    }
+}
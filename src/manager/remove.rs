@@ -0,0 +1,44 @@
+use std::io::Write;
+
+use color_eyre::eyre::eyre;
+use cote::prelude::*;
+
+use super::AppContext;
+
+#[derive(Debug, Cote)]
+#[cote(shellcomp, aborthelp, width = 50, overload, notexit)]
+pub struct Remove {
+    /// The index of configuration
+    #[pos()]
+    pub index: usize,
+}
+
+impl Remove {
+    pub async fn invoke_cmd(&self, ac: &mut AppContext) -> color_eyre::Result<()> {
+        if self.index >= ac.cfgs.len() {
+            return Err(eyre!(
+                "Index out of bound, load the configurations using command `load`"
+            ));
+        }
+        if let Some(inst) = ac.insts.iter().find(|v| v.index == self.index) {
+            return Err(eyre!(
+                "Configuration {} is used by instance {}, kill it first",
+                self.index,
+                inst.id
+            ));
+        }
+        ac.cfgs.remove(self.index);
+        // keep the instances point to their configurations
+        for inst in ac.insts.iter_mut().filter(|v| v.index > self.index) {
+            inst.index -= 1;
+            inst.supervisor.set_index(inst.index).await?;
+        }
+        writeln!(
+            ac.out,
+            "Removed configuration {}, the configurations after it are moved forward",
+            self.index
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use color_eyre::eyre::eyre;
+use cote::prelude::*;
+
+use crate::config::ConfigFile;
+use crate::config::DeployConfig;
+use crate::config::FileFormat;
+use crate::inherit::find_null;
+use crate::inherit::Inherit;
+
+use super::AppContext;
+
+#[derive(Debug, Cote)]
+#[cote(shellcomp, aborthelp, width = 50, overload, notexit)]
+pub struct Save {
+    /// Set the path of configuration, default is the loaded one
+    #[arg(alias = "-c")]
+    pub config: Option<String>,
+}
+
+impl Save {
+    pub async fn invoke_cmd(&self, ac: &mut AppContext) -> color_eyre::Result<()> {
+        let path = match &self.config {
+            Some(path) => ac.path(path)?.display().to_string(),
+            None => ac
+                .path
+                .clone()
+                .ok_or_else(|| eyre!("No configuration loaded, set the path with `--config`"))?,
+        };
+        let expanded = shellexpand::full(&path)?;
+
+        write_configs(Path::new(&*expanded), &ac.cfgs, &ac.inherit)?;
+        writeln!(
+            ac.out,
+            "Saved {} configuration(s) to `{expanded}`",
+            ac.cfgs.len()
+        )?;
+        ac.path = Some(path);
+
+        Ok(())
+    }
+}
+
+/// Write the configurations to a temporary file and rename it to `path`,
+/// the old file is kept as `{path}.bak`.
+///
+/// The entries only keep the fields different from the defaults and profiles they extend.
+pub fn write_configs(
+    path: &Path,
+    cfgs: &[DeployConfig],
+    inherit: &Inherit,
+) -> color_eyre::Result<()> {
+    let format = FileFormat::from_path(path);
+    let content = if inherit.is_empty() && format != FileFormat::Toml {
+        format.to_string(&cfgs)?
+    } else {
+        let configs = cfgs
+            .iter()
+            .map(|v| inherit.reduce(v))
+            .collect::<color_eyre::Result<Vec<_>>>()?;
+
+        if format == FileFormat::Toml {
+            // TOML has no null
+            if let Some((index, path)) = configs
+                .iter()
+                .enumerate()
+                .find_map(|(index, v)| find_null(v).map(|v| (index, v)))
+            {
+                return Err(eyre!(
+                    "TOML can not unset `{path}` of configuration {index}, which is set by the defaults or profile"
+                ));
+            }
+        }
+        format.to_string(&ConfigFile {
+            inherit: inherit.clone(),
+            configs,
+        })?
+    };
+    let name = path
+        .file_name()
+        .ok_or_else(|| eyre!("Invalid path `{}`", path.display()))?
+        .to_string_lossy();
+    let temp = path.with_file_name(format!(".{name}.tmp"));
+    // the configurations contain secrets
+    let mode = std::fs::metadata(path)
+        .map(|v| v.permissions().mode())
+        .unwrap_or(0o600);
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(mode)
+        .open(&temp)?;
+
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    if path.exists() {
+        std::fs::copy(path, path.with_file_name(format!("{name}.bak")))?;
+    }
+    std::fs::rename(&temp, path)?;
+
+    Ok(())
+}
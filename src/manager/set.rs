@@ -0,0 +1,58 @@
+use std::io::Write;
+
+use cote::prelude::*;
+
+use crate::validate::validate;
+
+use super::AppContext;
+
+#[derive(Debug, Cote)]
+#[cote(shellcomp, aborthelp, width = 50, overload, notexit)]
+pub struct SetField {
+    /// The index of configuration
+    #[pos()]
+    pub index: usize,
+
+    /// The path of field, such as `kcp_cfg.mode`
+    #[pos()]
+    pub path: String,
+
+    /// The new value, `none` unset the optional field
+    #[pos()]
+    pub value: String,
+}
+
+impl SetField {
+    pub async fn invoke_cmd(&self, ac: &mut AppContext) -> color_eyre::Result<()> {
+        let deploy_cfg = ac.cfgs.get_mut(self.index).ok_or_else(|| {
+            color_eyre::Report::msg(
+                "Index out of bound, load the configurations using command `load`",
+            )
+        })?;
+
+        deploy_cfg.set(&self.path, &self.value)?;
+        writeln!(
+            ac.out,
+            "Set `{}` of configuration {}, use `save` to write it back",
+            self.path, self.index
+        )?;
+        print_issues(ac, self.index)?;
+        if ac.insts.iter().any(|v| v.index == self.index) {
+            writeln!(ac.out, "Restart the instances to apply the change")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Print the issues of configuration at `index`.
+pub fn print_issues(ac: &mut AppContext, index: usize) -> color_eyre::Result<()> {
+    let prefix = format!("$[{index}].");
+
+    for issue in validate(&ac.cfgs) {
+        if issue.path.starts_with(&prefix) {
+            writeln!(ac.out, "{issue}")?;
+        }
+    }
+    Ok(())
+}
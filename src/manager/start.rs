@@ -1,14 +1,31 @@
-use std::env::temp_dir;
+use std::io::Write;
 use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
 
 use cote::prelude::*;
-use tokio::fs::{create_dir_all, read_to_string, write};
-use tokio::process::Command;
-
-use crate::config::{KcpMode, Method, SsConfig};
-
+use tokio::fs::{create_dir_all, read_to_string};
+use tokio::process::Child;
+use tokio::time::sleep;
+
+use crate::capture::Capture;
+use crate::config::{
+    default_kcp_port, Crypt, DeployConfig, FileFormat, KcpConfig, KcpMode, LogMode, Method,
+    RestartPolicy, Secret, SsConfig,
+};
+use crate::procfs::Listen;
+use crate::state::{
+    create_private_dir, record_path, remove_configs, runtime_dir, write_private, InstanceRecord,
+    ProcRecord,
+};
+use crate::supervisor::{Proc, ProcSpec, Supervisor};
+use crate::template::Vars;
+
+use super::logs::{last_lines, Source, DEFAULT_LINES};
 use super::AppContext;
 
+/// Time to wait before checking the processes are still running.
+const READY_DELAY: Duration = Duration::from_millis(500);
+
 #[derive(Debug, Cote)]
 #[cote(shellcomp, aborthelp, width = 50, overload, notexit)]
 pub struct Start {
@@ -57,28 +74,38 @@ pub struct Start {
     #[arg(alias = "-l")]
     pub listen: Option<u32>,
 
+    /// Set the address of ssserver forwarded by kcptun
+    pub kcp_server: Option<String>,
+
+    /// Set kcptun crypt
+    #[arg(scvalues = Crypt::values())]
+    pub crypt: Option<Crypt>,
+
+    /// Set kcptun key
+    pub kcp_key: Option<String>,
+
     /// Set send windows size
-    #[arg(alias = "-sw", value = 2048u32, scvalues = ["2048"])]
+    #[arg(alias = "-sw", scvalues = ["2048"])]
     pub send_wnd: Option<u32>,
 
     /// Set receive windows size
-    #[arg(alias = "-rw", value = 2048u32, scvalues = ["2048"])]
+    #[arg(alias = "-rw", scvalues = ["2048"])]
     pub recv_wnd: Option<u32>,
 
     /// Set mtu value
-    #[arg(value = 1400u32, scvalues = ["1400"])]
+    #[arg(scvalues = ["1400"])]
     pub mtu: Option<u32>,
 
     /// Set dscp value
-    #[arg(value = 46u32, scvalues = ["46"])]
+    #[arg(scvalues = ["46"])]
     pub dscp: Option<u32>,
 
     /// Set datashard value
-    #[arg(alias = "-ds", value = 30u32, scvalues = ["30"])]
+    #[arg(alias = "-ds", scvalues = ["30"])]
     pub data_shard: Option<u32>,
 
     /// Set parityshard value
-    #[arg(alias = "-ps", value = 15u32, scvalues = ["15"])]
+    #[arg(alias = "-ps", scvalues = ["15"])]
     pub parity_shard: Option<u32>,
 
     /// Set kcptun mode
@@ -88,56 +115,252 @@ pub struct Start {
     /// Enable compress mode
     pub compress: bool,
 
+    /// Disable compress mode
+    pub no_compress: bool,
+
     /// Set the log file path of kcp
     pub kcp_log: Option<PathBuf>,
 
+    /// Append to or truncate the log files
+    #[arg(scvalues = LogMode::values())]
+    pub log_mode: Option<LogMode>,
+
+    /// Set the restart policy: never, on-failure or always
+    #[arg(alias = "-r", scvalues = RestartPolicy::values())]
+    pub restart: Option<RestartPolicy>,
+
+    /// Set the maximum number of consecutive restarts
+    pub max_retries: Option<u32>,
+
+    /// Set the initial restart backoff in milliseconds
+    pub backoff: Option<u64>,
+
+    /// Save the overrides to the configuration, use `save` to write them back
+    pub save: bool,
+
     /// The index of configuration
     #[pos()]
     pub index: usize,
 }
 
+/// The command line flags of kcptun override the [`KcpConfig`].
+#[derive(Debug, Clone, Default)]
+pub struct KcpFlags {
+    pub server: Option<String>,
+
+    pub crypt: Option<Crypt>,
+
+    pub key: Option<String>,
+
+    pub send_wnd: Option<u32>,
+
+    pub recv_wnd: Option<u32>,
+
+    pub mtu: Option<u32>,
+
+    pub dscp: Option<u32>,
+
+    pub data_shard: Option<u32>,
+
+    pub parity_shard: Option<u32>,
+
+    pub mode: Option<KcpMode>,
+
+    /// `Some(false)` disables the compression even if it is enabled in configuration
+    pub compress: Option<bool>,
+}
+
+impl KcpFlags {
+    /// Resolve the effective kcptun settings of server, the client must use the same settings.
+    pub fn resolve(&self, cfg: &KcpConfig) -> KcpConfig {
+        KcpConfig {
+            server: self.server.clone().unwrap_or(cfg.server.clone()),
+            crypt: self.crypt.unwrap_or(cfg.crypt),
+            key: self.key.as_deref().map_or(cfg.key.clone(), Secret::from),
+            send_wnd: self.send_wnd.unwrap_or(cfg.send_wnd),
+            recv_wnd: self.recv_wnd.unwrap_or(cfg.recv_wnd),
+            mtu: self.mtu.unwrap_or(cfg.mtu),
+            mode: self.mode.unwrap_or(cfg.mode),
+            dscp: self.dscp.unwrap_or(cfg.dscp),
+            data_shard: self.data_shard.unwrap_or(cfg.data_shard),
+            parity_shard: self.parity_shard.unwrap_or(cfg.parity_shard),
+            comp: self.compress.unwrap_or(cfg.comp),
+        }
+    }
+}
+
 impl Start {
+    pub fn kcp_flags(&self) -> KcpFlags {
+        KcpFlags {
+            server: self.kcp_server.clone(),
+            crypt: self.crypt,
+            key: self.kcp_key.clone(),
+            send_wnd: self.send_wnd,
+            recv_wnd: self.recv_wnd,
+            mtu: self.mtu,
+            dscp: self.dscp,
+            data_shard: self.data_shard,
+            parity_shard: self.parity_shard,
+            mode: self.mode,
+            compress: match (self.compress, self.no_compress) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => None,
+            },
+        }
+    }
+
+    /// Apply the overrides of command line to the configuration.
+    pub fn save_overrides(&self, cfg: &mut DeployConfig) {
+        let ss_cfg = &mut cfg.ss_cfg;
+
+        ss_cfg.server_port = self.port.unwrap_or(ss_cfg.server_port);
+        if let Some(password) = &self.password {
+            ss_cfg.password = password.as_str().into();
+        }
+        ss_cfg.timeout = self.timeout.unwrap_or(ss_cfg.timeout);
+        ss_cfg.method = self.method.unwrap_or(ss_cfg.method);
+        ss_cfg.fast_open |= self.fast_open;
+        cfg.bin = self.bin.clone().unwrap_or(cfg.bin.clone());
+        cfg.kcp = self.kcp.clone().unwrap_or(cfg.kcp.clone());
+        cfg.out_log = self.out_log.clone().or(cfg.out_log.clone());
+        cfg.err_log = self.err_log.clone().or(cfg.err_log.clone());
+        cfg.kcp_log = self.kcp_log.clone().or(cfg.kcp_log.clone());
+        cfg.restart.policy = self.restart.unwrap_or(cfg.restart.policy);
+        cfg.restart.max_retries = self.max_retries.unwrap_or(cfg.restart.max_retries);
+        cfg.restart.backoff = self.backoff.unwrap_or(cfg.restart.backoff);
+        cfg.log.mode = self.log_mode.unwrap_or(cfg.log.mode);
+        if let Some(kcp_cfg) = cfg.kcp_cfg.as_mut() {
+            *kcp_cfg = self.kcp_flags().resolve(kcp_cfg);
+        }
+    }
+
     pub async fn invoke_cmd(&self, ac: &mut AppContext) -> color_eyre::Result<()> {
-        let deploy_cfg = ac.cfgs.get(self.index).ok_or_else(|| {
+        let deploy_cfg = ac.cfgs.get(self.index).cloned().ok_or_else(|| {
             color_eyre::Report::msg(
                 "Index out of bound, load the configurations using command `load`",
             )
         })?;
 
-        let bin = self.bin.as_ref().unwrap_or(&deploy_cfg.bin);
-        let bin = shellexpand::path::full(bin.as_path())?;
-        let mut cmd = Command::new(&*bin);
+        if self.compress && self.no_compress {
+            return Err(color_eyre::Report::msg(
+                "Can not set both `--compress` and `--no-compress`",
+            ));
+        }
+        if deploy_cfg.log.rotating() && !ac.capture {
+            return Err(color_eyre::Report::msg(format!(
+                "The logs of configuration {} are rotated through pipes closed when rssdeploy exits, start them by the daemon or unset `log.max_size` and `log.daily`",
+                self.index
+            )));
+        }
+        // resolve kcptun settings before start anything, it fails if kcptun is not configured
+        let kcp_cfg = if self.enable_kcp {
+            let cfg = deploy_cfg.kcp_cfg.as_ref().ok_or_else(|| {
+                color_eyre::Report::msg(format!(
+                    "No kcptun configuration at index {}, set it by `set {} kcp_cfg.<field> <value>`",
+                    self.index, self.index
+                ))
+            })?;
+            let mut cfg = self.kcp_flags().resolve(cfg);
+
+            cfg.key = cfg.key.resolve().await?.into();
+            Some(cfg)
+        } else {
+            None
+        };
+        let id = ac.alloc_id()?;
+        let mut launched = Launched::default();
+        let record = match self
+            .launch(ac, &deploy_cfg, kcp_cfg.as_ref(), id, &mut launched)
+            .await
+        {
+            Ok(record) => record,
+            Err(e) => {
+                launched.rollback().await;
+                return Err(e);
+            }
+        };
+        let store = ac.state.as_ref().map(|v| record_path(v, id));
+
+        // track the instance before writing the output, the client may be gone
+        ac.insts.push(crate::manager::SsInstance {
+            id,
+            index: self.index,
+            supervisor: Supervisor::new(
+                record,
+                launched.ss.map(Proc::Child),
+                launched.kcp.map(Proc::Child),
+                store,
+            ),
+        });
+        let _ = writeln!(ac.out, "Instance {id} started");
+        if self.save {
+            self.save_overrides(&mut ac.cfgs[self.index]);
+            let _ = writeln!(
+                ac.out,
+                "Overrides saved to configuration {}, use `save` to write them back",
+                self.index
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Write the configurations and spawn the processes, they are recorded in `launched`
+    /// so the caller can roll back if it fails.
+    async fn launch(
+        &self,
+        ac: &mut AppContext,
+        deploy_cfg: &DeployConfig,
+        kcp_cfg: Option<&KcpConfig>,
+        id: usize,
+        launched: &mut Launched,
+    ) -> color_eyre::Result<InstanceRecord> {
         let server_port;
+        let method;
+        let ss_config_path;
 
         if let Some(config) = self.config.as_ref() {
-            let config = shellexpand::path::full(config.as_path())?;
+            let config = ac.path(config)?;
+            let config = shellexpand::path::full(&config)?;
             let path = &*config;
-
-            cmd.arg("-c").arg(path.to_str().unwrap_or_default());
+            let format = FileFormat::from_path(path);
+            let value: serde_json::Value = format.parse(&read_to_string(path).await?)?;
 
             // read port
-            let ss_config: SsConfig = serde_json::from_str(&read_to_string(path).await?)?;
+            let ss_config: SsConfig = serde_json::from_value(value.clone())?;
 
             server_port = ss_config.server_port;
+            method = ss_config.method;
+            if format == FileFormat::Json {
+                ss_config_path = path.to_path_buf();
+            } else {
+                // ssserver only accepts JSON
+                ss_config_path = config_path(id, "ss")?;
+                write_private(&ss_config_path, serde_json::to_string_pretty(&value)?).await?;
+                launched.configs.push(ss_config_path.clone());
+            }
         } else {
             let ss_cfg = &deploy_cfg.ss_cfg;
 
             let server = &ss_cfg.server;
-            let password = self.password.as_ref().unwrap_or(&ss_cfg.password);
+            let password = match &self.password {
+                Some(password) => password.clone(),
+                None => ss_cfg.password.resolve().await?,
+            };
             let timeout = self.timeout.unwrap_or(ss_cfg.timeout);
-            let method = self.method.unwrap_or(ss_cfg.method);
             let fast_open = self.fast_open || ss_cfg.fast_open;
-            let temp_dir = temp_dir();
-            let temp_file = temp_dir.join(format!("ss_config_{}.json", self.index));
+            let temp_file = config_path(id, "ss")?;
 
             server_port = self.port.unwrap_or(ss_cfg.server_port);
+            method = self.method.unwrap_or(ss_cfg.method);
 
-            write(
+            write_private(
                 &temp_file,
                 serde_json::to_string_pretty(&SsConfig {
                     server: server.clone(),
                     server_port,
-                    password: password.to_string(),
+                    password: password.into(),
                     timeout,
                     method,
                     fast_open,
@@ -145,111 +368,350 @@ impl Start {
             )
             .await?;
 
-            cmd.arg("-c").arg(temp_file);
+            launched.configs.push(temp_file.clone());
+            ss_config_path = temp_file;
         }
-        let out_log = self.out_log.as_ref().or(self.out_log.as_ref());
-        let out_log = out_log.and_then(|v| shellexpand::path::full(v).ok());
-        let err_log = self.err_log.as_ref().or(self.err_log.as_ref());
-        let err_log = err_log.and_then(|v| shellexpand::path::full(v).ok());
-
-        if let Some(out_log) = out_log {
-            if let Some(out_log) = out_log.parent() {
-                create_dir_all(out_log).await?
-            }
-            cmd.stdout(
-                std::fs::File::options()
-                    .write(true)
-                    .truncate(true)
-                    .create(true)
-                    .open(out_log)?,
-            );
+
+        let kcp_port = match self.listen {
+            Some(listen) => listen,
+            None if kcp_cfg.is_none() => default_kcp_port(server_port).unwrap_or_default(),
+            None => default_kcp_port(server_port).ok_or_else(|| {
+                color_eyre::Report::msg(format!(
+                    "Invalid kcptun port {}, set it by `--listen`",
+                    u64::from(server_port) + 1
+                ))
+            })?,
+        };
+        let vars = Vars {
+            index: self.index,
+            instance_id: id,
+            port: server_port,
+            kcp_port,
+            method,
+        };
+        let bin = self.bin.as_ref().unwrap_or(&deploy_cfg.bin);
+        let mut cmd = ProcSpec::new(ac.bin_path(vars.expand(bin)?)?);
+
+        cmd.arg("-c").arg(&ss_config_path);
+
+        let log_dir = ac.log_dir.as_deref();
+        let log = &deploy_cfg.log;
+        let truncate = self.log_mode.unwrap_or(log.mode) == LogMode::Truncate;
+
+        cmd.stdout = log_path(
+            ac,
+            &vars,
+            self.out_log.as_ref().or(deploy_cfg.out_log.as_ref()),
+            log_dir.map(|v| v.join("ss_{instance_id}.out.log")),
+        )
+        .await?;
+        cmd.stderr = log_path(
+            ac,
+            &vars,
+            self.err_log.as_ref().or(deploy_cfg.err_log.as_ref()),
+            log_dir.map(|v| v.join("ss_{instance_id}.err.log")),
+        )
+        .await?;
+
+        let mut restart = deploy_cfg.restart.clone();
+
+        restart.policy = self.restart.unwrap_or(restart.policy);
+        restart.max_retries = self.max_retries.unwrap_or(restart.max_retries);
+        restart.backoff = self.backoff.unwrap_or(restart.backoff);
+
+        cmd.log = log.clone();
+        cmd.capture = ac.capture.then(Capture::default);
+        writeln!(ac.out, "start cmd => {cmd}")?;
+
+        let ss_end = stderr_end(&cmd, truncate).await;
+
+        launched.ss = Some(spawn("ssserver", &cmd, truncate)?);
+        let ss_spec = cmd;
+        let mut kcp = None;
+
+        if let Some(cfg) = kcp_cfg {
+            let bin = self.kcp.as_ref().unwrap_or(&deploy_cfg.kcp);
+            let mut cmd = ProcSpec::new(ac.bin_path(vars.expand(bin)?)?);
+
+            // listen to ss server and port
+            let kcp_server = format!("{}:{}", cfg.server, server_port);
+            let kcp_log = log_path(
+                ac,
+                &vars,
+                self.kcp_log.as_ref().or(deploy_cfg.kcp_log.as_ref()),
+                log_dir.map(|v| v.join("kcp_{instance_id}.log")),
+            )
+            .await?;
+
+            let kcp_config_path = config_path(id, "kcp")?;
+
+            write_private(
+                &kcp_config_path,
+                serde_json::to_string_pretty(
+                    &cfg.server_config(&format!(":{kcp_port}"), &kcp_server),
+                )?,
+            )
+            .await?;
+            launched.configs.push(kcp_config_path.clone());
+            cmd.arg("-c").arg(kcp_config_path);
+            // kcptun writes the log to stderr, capture the stdout too
+            cmd.stdout = kcp_log.clone();
+            cmd.stderr = kcp_log;
+            cmd.log = log.clone();
+            cmd.capture = ac.capture.then(Capture::shared);
+            writeln!(ac.out, "start cmd => {cmd}")?;
+            writeln!(
+                ac.out,
+                "kcptun settings => listen :{kcp_port} target {kcp_server} {}",
+                KcpConfig {
+                    key: cfg.key.redacted(),
+                    ..cfg.clone()
+                }
+                .tuning_args()
+                .join(" ")
+            )?;
+
+            let kcp_end = stderr_end(&cmd, truncate).await;
+
+            launched.kcp = Some(spawn("kcptun", &cmd, truncate)?);
+            kcp = Some((cmd, kcp_port, kcp_end));
         }
-        if let Some(err_log) = err_log {
-            if let Some(err_log) = err_log.parent() {
-                create_dir_all(err_log).await?
-            }
 
-            cmd.stderr(
-                std::fs::File::options()
-                    .write(true)
-                    .truncate(true)
-                    .create(true)
-                    .open(err_log)?,
-            );
+        // the process may exit at once because of bad key or port in use
+        sleep(READY_DELAY).await;
+        check_exited("ssserver", &ss_spec, launched.ss.as_mut(), ss_end).await?;
+        if let Some((spec, _, end)) = &kcp {
+            check_exited("kcptun", spec, launched.kcp.as_mut(), *end).await?;
         }
 
-        println!("start cmd => {cmd:?}");
+        Ok(InstanceRecord {
+            id,
+            index: self.index,
+            started: SystemTime::now(),
+            configs: launched.configs.clone(),
+            restart,
+            ss: ProcRecord {
+                pid: launched.ss.as_ref().and_then(Child::id),
+                spec: ss_spec,
+                ports: Vec::from_iter(u16::try_from(server_port).ok().map(Listen::Tcp)),
+            },
+            kcp: kcp.map(|(spec, port, _)| ProcRecord {
+                pid: launched.kcp.as_ref().and_then(Child::id),
+                spec,
+                ports: Vec::from_iter(u16::try_from(port).ok().map(Listen::Udp)),
+            }),
+        })
+    }
+}
 
-        let ss = cmd.spawn()?;
-        let mut kcp = None;
+/// The configurations and processes created by `start`.
+#[derive(Debug, Default)]
+struct Launched {
+    configs: Vec<PathBuf>,
 
-        if self.enable_kcp {
-            if let Some(cfg) = &deploy_cfg.kcp_cfg {
-                let bin = self.kcp.as_ref().unwrap_or(&deploy_cfg.kcp);
-                let bin = shellexpand::path::full(bin.as_path())?;
-                let mut cmd = Command::new(&*bin);
-
-                // listen to ss server and port
-                let kcp_server = format!("{}:{}", cfg.server, server_port);
-                let kcp_port = self.listen.unwrap_or(server_port + 1);
-                let send_wnd = self.send_wnd.unwrap_or(cfg.send_wnd);
-                let recv_wnd = self.recv_wnd.unwrap_or(cfg.recv_wnd);
-                let dscp = self.dscp.unwrap_or(cfg.dscp);
-                let ds = self.data_shard.unwrap_or(cfg.data_shard);
-                let ps = self.parity_shard.unwrap_or(cfg.parity_shard);
-                let mode = self.mode.unwrap_or(cfg.mode);
-                let mtu = self.mtu.unwrap_or(cfg.mtu);
-                let no_comp = !self.compress || cfg.comp;
-                let kcp_log = self.kcp_log.as_ref().or(self.kcp_log.as_ref());
-                let kcp_log = kcp_log.and_then(|v| shellexpand::path::full(v).ok());
-
-                cmd.arg("-l")
-                    .arg(format!(":{kcp_port}"))
-                    .arg("-t")
-                    .arg(kcp_server)
-                    .arg("-crypt")
-                    .arg(cfg.crypt.to_string())
-                    .arg("-key")
-                    .arg(&cfg.key)
-                    .arg("-sndwnd")
-                    .arg(send_wnd.to_string())
-                    .arg("-rcvwnd")
-                    .arg(recv_wnd.to_string())
-                    .arg("-dscp")
-                    .arg(dscp.to_string())
-                    .arg("-datashard")
-                    .arg(ds.to_string())
-                    .arg("-parityshard")
-                    .arg(ps.to_string())
-                    .arg("-mode")
-                    .arg(mode.to_string())
-                    .arg("-mtu")
-                    .arg(mtu.to_string());
-                if no_comp {
-                    cmd.arg("-nocomp");
-                }
-                if let Some(kcp_log) = kcp_log {
-                    if let Some(kcp_log) = kcp_log.parent() {
-                        create_dir_all(kcp_log).await?
-                    }
-                    cmd.stderr(
-                        std::fs::File::options()
-                            .write(true)
-                            .truncate(true)
-                            .create(true)
-                            .open(kcp_log)?,
-                    );
-                }
-                kcp = Some(cmd.spawn()?);
+    ss: Option<Child>,
+
+    kcp: Option<Child>,
+}
+
+impl Launched {
+    /// Kill the processes and remove the configurations, nothing is left if `start` failed.
+    async fn rollback(self) {
+        for mut child in self.ss.into_iter().chain(self.kcp) {
+            let _ = child.kill().await;
+        }
+        remove_configs(&self.configs);
+    }
+}
+
+/// Spawn the process, the error tells which executable failed.
+fn spawn(name: &str, spec: &ProcSpec, truncate: bool) -> color_eyre::Result<Child> {
+    spec.spawn(truncate).map_err(|e| {
+        color_eyre::Report::msg(format!(
+            "Failed to start {name} `{}`: {e}",
+            spec.bin.display()
+        ))
+    })
+}
+
+/// Offset of the end of stderr before spawn, the output after it is written by the process.
+async fn stderr_end(spec: &ProcSpec, truncate: bool) -> u64 {
+    match Source::of(spec, true) {
+        Some(source) if !truncate => source.end().await.unwrap_or_default(),
+        _ => 0,
+    }
+}
+
+/// Return an error with the last lines of stderr if the process already exited.
+async fn check_exited(
+    name: &str,
+    spec: &ProcSpec,
+    child: Option<&mut Child>,
+    offset: u64,
+) -> color_eyre::Result<()> {
+    let Some(status) = child.map(Child::try_wait).transpose()?.flatten() else {
+        return Ok(());
+    };
+    let mut msg = format!("{name} exited immediately with {status}");
+
+    if let Some(source) = Source::of(spec, true) {
+        if let Ok((data, _)) = source.since(offset).await {
+            let tail = last_lines(&data, DEFAULT_LINES);
+
+            if !tail.is_empty() {
+                msg.push_str(", the last output:\n");
+                msg.push_str(String::from_utf8_lossy(tail).trim_end());
             }
         }
+    }
+    Err(color_eyre::Report::msg(msg))
+}
 
-        ac.insts.push(crate::manager::SsInstance {
-            id: self.index,
-            ss,
-            kcp,
-        });
+/// Resolve the log path set by `start` or configuration, then the one in default directory.
+///
+/// The variables of path are expanded, and the parent directory is created.
+/// The relative path is resolved against the working directory of client.
+async fn log_path(
+    ac: &AppContext,
+    vars: &Vars,
+    path: Option<&PathBuf>,
+    default: Option<PathBuf>,
+) -> color_eyre::Result<Option<PathBuf>> {
+    let Some(path) = path.cloned().or(default) else {
+        return Ok(None);
+    };
+    let path = ac.path(vars.expand(&path)?)?;
+
+    if let Some(dir) = path.parent() {
+        create_dir_all(dir).await?;
+    }
+    Ok(Some(path))
+}
 
-        Ok(())
+/// Path of the configuration generated for the instance, such as `instance_0_ss.json`.
+fn config_path(id: usize, name: &str) -> std::io::Result<PathBuf> {
+    let dir = runtime_dir();
+
+    create_private_dir(&dir)?;
+    Ok(dir.join(format!("instance_{id}_{name}.json")))
+}
+
+#[cfg(test)]
+mod tests {
+    use cote::prelude::*;
+
+    use super::*;
+
+    fn start(args: &[&str]) -> Start {
+        let args: Vec<_> = std::iter::once("app").chain(args.iter().copied()).collect();
+
+        Start::parse(Args::from(args)).unwrap()
+    }
+
+    fn config(comp: bool) -> KcpConfig {
+        KcpConfig {
+            comp,
+            ..KcpConfig::kcptun_default()
+        }
+    }
+
+    #[test]
+    fn resolve_without_flags() {
+        let cfg = config(false);
+        let resolved = KcpFlags::default().resolve(&cfg);
+
+        assert_eq!(
+            serde_json::to_value(&resolved).unwrap(),
+            serde_json::to_value(&cfg).unwrap()
+        );
+    }
+
+    #[test]
+    fn resolve_every_field() {
+        let flags = start(&[
+            "--kcp-server",
+            "10.0.0.1",
+            "--crypt",
+            "salsa20",
+            "--kcp-key",
+            "secret",
+            "--send-wnd",
+            "2048",
+            "--recv-wnd",
+            "4096",
+            "--mtu",
+            "1400",
+            "--dscp",
+            "46",
+            "--data-shard",
+            "30",
+            "--parity-shard",
+            "15",
+            "--mode",
+            "fast3",
+            "--no-compress",
+            "0",
+        ])
+        .kcp_flags();
+        let resolved = flags.resolve(&config(true));
+
+        assert_eq!(resolved.server, "10.0.0.1");
+        assert_eq!(resolved.crypt, Crypt::Salsa20);
+        assert_eq!(resolved.key, Secret::from("secret"));
+        assert_eq!(resolved.send_wnd, 2048);
+        assert_eq!(resolved.recv_wnd, 4096);
+        assert_eq!(resolved.mtu, 1400);
+        assert_eq!(resolved.dscp, 46);
+        assert_eq!(resolved.data_shard, 30);
+        assert_eq!(resolved.parity_shard, 15);
+        assert_eq!(resolved.mode, KcpMode::Fast3);
+        assert!(!resolved.comp);
+    }
+
+    #[test]
+    fn resolve_compress() {
+        for (args, comp, expect) in [
+            (&[][..], true, true),
+            (&[][..], false, false),
+            (&["--compress"][..], false, true),
+            (&["--compress"][..], true, true),
+            (&["--no-compress"][..], true, false),
+            (&["--no-compress"][..], false, false),
+        ] {
+            let mut args = args.to_vec();
+
+            args.push("0");
+            assert_eq!(
+                start(&args).kcp_flags().resolve(&config(comp)).comp,
+                expect,
+                "{args:?} with comp: {comp}"
+            );
+        }
+    }
+
+    #[test]
+    fn compress_enabled_by_config() {
+        let cfg = config(true);
+
+        assert!(KcpFlags::default().resolve(&cfg).comp);
+        assert!(!cfg.tuning_args().contains(&String::from("-nocomp")));
+        assert!(config(false)
+            .tuning_args()
+            .contains(&String::from("-nocomp")));
+    }
+
+    #[test]
+    fn save_overrides_resolve_kcp() {
+        let mut cfg = DeployConfig {
+            kcp_cfg: Some(config(true)),
+            ..DeployConfig::default()
+        };
+
+        start(&["--mode", "fast2", "--no-compress", "0"]).save_overrides(&mut cfg);
+
+        let kcp_cfg = cfg.kcp_cfg.unwrap();
+
+        assert_eq!(kcp_cfg.mode, KcpMode::Fast2);
+        assert!(!kcp_cfg.comp);
     }
 }
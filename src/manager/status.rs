@@ -0,0 +1,201 @@
+use std::io::Write;
+use std::time::Duration;
+
+use cote::prelude::*;
+use prettytable::{Row, Table};
+use tokio::select;
+use tokio::time::sleep;
+
+use crate::procfs::bound_ports;
+use crate::procfs::usage;
+use crate::supervisor::ProcState;
+
+use super::cancelled;
+use super::format::Format;
+use super::format::InstanceInfo;
+use super::format_duration;
+use super::AppContext;
+use super::Follow;
+
+#[derive(Debug, Clone, Cote)]
+#[cote(shellcomp, aborthelp, width = 50, overload, notexit)]
+pub struct Status {
+    /// Show the status of the instance with given instance id
+    #[arg(alias = "-i")]
+    pub id: Option<usize>,
+
+    /// Refresh the status until ctrl-c pressed
+    #[arg(alias = "-w")]
+    pub watch: bool,
+
+    /// Set the refresh interval of watch mode in seconds
+    #[arg(value = 2u64)]
+    pub interval: Option<u64>,
+
+    /// Set the output format: table, json or yaml
+    #[arg(alias = "-f", scvalues = Format::values())]
+    pub format: Option<Format>,
+}
+
+impl Status {
+    pub async fn invoke_cmd(&self, ac: &mut AppContext) -> color_eyre::Result<()> {
+        if let Some(id) = self.id {
+            if !ac.insts.iter().any(|v| v.id == id) {
+                return Err(color_eyre::Report::msg(format!("Instance {id} not found")));
+            }
+        }
+        let mut out = ac.out.clone();
+
+        if !self.watch {
+            return self.display(ac, &mut out);
+        }
+
+        let interval = Duration::from_secs(self.interval.unwrap_or(2).max(1));
+        let table = self.format.unwrap_or_default() == Format::Table;
+        let access = ac.access.tx.clone();
+        let mut cancel = ac.cancel.take();
+        let status = self.clone();
+
+        ac.follow = Some(Follow::new(async move {
+            loop {
+                let status = status.clone();
+                let frame = access
+                    .run(move |ac| {
+                        let mut frame = vec![];
+
+                        status.display(ac, &mut frame).map(|_| frame)
+                    })
+                    .await??;
+
+                if table {
+                    // clear the screen and move cursor to top left
+                    write!(out, "\x1b[2J\x1b[H")?;
+                }
+                out.write_all(&frame)?;
+                if table {
+                    writeln!(
+                        out,
+                        "Refresh every {}s, press ctrl-c to stop",
+                        interval.as_secs()
+                    )?;
+                }
+                out.flush()?;
+                select! {
+                    _ = sleep(interval) => { }
+                    _ = cancelled(&mut cancel) => break,
+                }
+            }
+            Ok(())
+        }));
+        Ok(())
+    }
+
+    fn display(&self, ac: &AppContext, out: &mut impl Write) -> color_eyre::Result<()> {
+        let format = self.format.unwrap_or_default();
+
+        if format != Format::Table {
+            let insts: Vec<_> = ac
+                .insts
+                .iter()
+                .filter(|v| self.id.is_none_or(|id| id == v.id))
+                .map(InstanceInfo::new)
+                .collect();
+
+            return format.write(out, &insts);
+        }
+        let mut table = Table::new();
+
+        table.add_row(Row::from([
+            "Id", "Config", "Process", "State", "Pid", "Uptime", "RSS", "CPU", "Restarts", "Ports",
+        ]));
+        for inst in ac.insts.iter() {
+            if self.id.is_some_and(|v| v != inst.id) {
+                continue;
+            }
+            let procs = [
+                ("ssserver", Some(inst.supervisor.ss())),
+                ("kcptun", inst.supervisor.kcp()),
+            ];
+
+            for (name, state) in procs {
+                if let Some(state) = state {
+                    let mut row = vec![
+                        inst.id.to_string(),
+                        inst.index.to_string(),
+                        name.to_string(),
+                    ];
+
+                    row.extend(display_state(&state));
+                    table.add_row(Row::from(row));
+                }
+            }
+        }
+        table.print(out)?;
+
+        Ok(())
+    }
+}
+
+/// Display the columns from State to Ports.
+fn display_state(state: &ProcState) -> Vec<String> {
+    let exit_code = state
+        .exited
+        .and_then(|v| v.status)
+        .map(|v| match v.code() {
+            Some(code) => format!("exited({code})"),
+            None => format!("exited({v})"),
+        })
+        .unwrap_or_else(|| String::from("exited"));
+
+    match state.pid {
+        Some(pid) => {
+            let usage = usage(pid);
+            let bound = bound_ports(pid);
+            let ports = state
+                .ports
+                .iter()
+                .map(|v| {
+                    if bound.contains(v) {
+                        format!("{v} bound")
+                    } else {
+                        format!("{v} unbound")
+                    }
+                })
+                .collect::<Vec<_>>();
+            let last = state.exited.map(|_| format!(", last {exit_code}"));
+
+            vec![
+                format!("running{}", last.unwrap_or_default()),
+                pid.to_string(),
+                format_duration(state.uptime()),
+                usage.map(|v| format_size(v.rss)).unwrap_or_default(),
+                usage
+                    .map(|v| format!("{:.2}s", v.cpu.as_secs_f64()))
+                    .unwrap_or_default(),
+                state.restarts.to_string(),
+                ports.join("\n"),
+            ]
+        }
+        None => vec![
+            exit_code,
+            String::from("-"),
+            String::from("-"),
+            String::from("-"),
+            String::from("-"),
+            state.restarts.to_string(),
+            String::from("-"),
+        ],
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "K", "M", "G"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1}{}", UNITS[unit])
+}
@@ -0,0 +1,116 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::time::Duration;
+
+use nix::unistd::sysconf;
+use nix::unistd::SysconfVar;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// A port the process expected to bind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Listen {
+    Tcp(u16),
+
+    Udp(u16),
+}
+
+impl Display for Listen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Listen::Tcp(port) => write!(f, "tcp/{port}"),
+            Listen::Udp(port) => write!(f, "udp/{port}"),
+        }
+    }
+}
+
+/// Resource usage of a process.
+#[derive(Debug, Clone, Copy)]
+pub struct Usage {
+    /// Resident set size in bytes
+    pub rss: u64,
+
+    /// User and system cpu time
+    pub cpu: Duration,
+}
+
+/// Read the resource usage from `/proc/<pid>/stat` and `/proc/<pid>/status`.
+pub fn usage(pid: u32) -> Option<Usage> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    let status = std::fs::read_to_string(format!("/proc/{pid}/status")).ok()?;
+    // the fields after comm, start from the state which is the 3rd field
+    let fields: Vec<_> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    let ticks = sysconf(SysconfVar::CLK_TCK).ok().flatten().unwrap_or(100) as u64;
+    let rss = status
+        .lines()
+        .find_map(|v| v.strip_prefix("VmRSS:"))
+        .and_then(|v| v.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+        .unwrap_or_default();
+
+    Some(Usage {
+        rss: rss * 1024,
+        cpu: Duration::from_millis((utime + stime) * 1000 / ticks.max(1)),
+    })
+}
+
+/// Ports bound by the sockets of process `pid`, the tcp port is bound if it is listening.
+pub fn bound_ports(pid: u32) -> HashSet<Listen> {
+    let mut ports = HashSet::new();
+    // the tables list every socket in the network namespace
+    let inodes = socket_inodes(pid);
+    // the tcp socket in LISTEN state is 0A
+    let tables = [
+        ("tcp", Some("0A")),
+        ("tcp6", Some("0A")),
+        ("udp", None),
+        ("udp6", None),
+    ];
+
+    for (name, state) in tables {
+        let listen = if name.starts_with("tcp") {
+            Listen::Tcp
+        } else {
+            Listen::Udp
+        };
+        let Ok(content) = std::fs::read_to_string(format!("/proc/{pid}/net/{name}")) else {
+            continue;
+        };
+        // skip the header line
+        for line in content.lines().skip(1) {
+            let fields: Vec<_> = line.split_whitespace().collect();
+            let port = fields
+                .get(1)
+                .and_then(|v| v.rsplit_once(':'))
+                .and_then(|(_, v)| u16::from_str_radix(v, 16).ok());
+
+            let owned = fields.get(9).is_some_and(|v| inodes.contains(*v));
+
+            if let Some(port) = port.filter(|_| owned) {
+                if state.is_none() || fields.get(3).copied() == state {
+                    ports.insert(listen(port));
+                }
+            }
+        }
+    }
+    ports
+}
+
+/// Inodes of the sockets opened by process `pid`, the fd links to `socket:[inode]`.
+fn socket_inodes(pid: u32) -> HashSet<String> {
+    let Ok(entries) = std::fs::read_dir(format!("/proc/{pid}/fd")) else {
+        return HashSet::new();
+    };
+
+    entries
+        .flatten()
+        .filter_map(|v| std::fs::read_link(v.path()).ok())
+        .filter_map(|v| {
+            v.to_str()
+                .and_then(|v| v.strip_prefix("socket:["))
+                .and_then(|v| v.strip_suffix(']'))
+                .map(String::from)
+        })
+        .collect()
+}
@@ -0,0 +1,140 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::thread::JoinHandle;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::config::LogConfig;
+use crate::template::date;
+use crate::template::today;
+
+/// The log file rotated by size or date, the rotated files are named `{path}.1`, `{path}.2` ...
+#[derive(Debug)]
+pub struct RotatingFile {
+    pub path: PathBuf,
+
+    cfg: LogConfig,
+
+    file: File,
+
+    /// Size of current file
+    size: u64,
+
+    /// Date of the content in current file
+    date: String,
+
+    /// Compression of the last rotated file, the output is not blocked by it
+    compressing: Option<JoinHandle<()>>,
+}
+
+impl RotatingFile {
+    pub fn open(path: &Path, cfg: &LogConfig, truncate: bool) -> std::io::Result<Self> {
+        let file = open(path, truncate)?;
+        let meta = file.metadata()?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            cfg: cfg.clone(),
+            // the file may be written in previous days
+            date: meta.modified().map(date).unwrap_or_else(|_| today()),
+            size: meta.len(),
+            file,
+            compressing: None,
+        })
+    }
+
+    pub fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        if self.need_rotate(buf.len() as u64) {
+            self.rotate()?;
+        }
+        self.file.write_all(buf)?;
+        self.size += buf.len() as u64;
+        Ok(())
+    }
+
+    fn need_rotate(&self, len: u64) -> bool {
+        let size = self.cfg.max_size.is_some_and(|v| self.size + len > v);
+        let daily = self.cfg.daily && self.date != today();
+
+        self.size > 0 && (size || daily)
+    }
+
+    /// Path of the `index`-th rotated file.
+    fn rotated(&self, index: u32, gz: bool) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+
+        path.push(format!(".{index}"));
+        if gz {
+            path.push(".gz");
+        }
+        PathBuf::from(path)
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let keep = self.cfg.keep;
+
+        // the rotated files are renamed below
+        if let Some(task) = self.compressing.take() {
+            let _ = task.join();
+        }
+        if keep > 0 {
+            // the files may be compressed or not if the setting changed
+            for gz in [false, true] {
+                remove(&self.rotated(keep, gz))?;
+                for index in (1..keep).rev() {
+                    rename(&self.rotated(index, gz), &self.rotated(index + 1, gz))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated(1, false))?;
+        }
+        self.file = open(&self.path, true)?;
+        self.size = 0;
+        self.date = today();
+        if keep > 0 && self.cfg.compress {
+            let from = self.rotated(1, false);
+            let to = self.rotated(1, true);
+
+            self.compressing = Some(std::thread::spawn(move || {
+                if let Err(e) = compress(&from, &to) {
+                    eprintln!("WARN! Failed compress log `{}`: {e:?}", from.display());
+                }
+            }));
+        }
+        Ok(())
+    }
+}
+
+fn open(path: &Path, truncate: bool) -> std::io::Result<File> {
+    File::options()
+        .write(true)
+        .append(!truncate)
+        .truncate(truncate)
+        .create(true)
+        .open(path)
+}
+
+fn remove(path: &Path) -> std::io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn rename(from: &Path, to: &Path) -> std::io::Result<()> {
+    match std::fs::rename(from, to) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Compress the file `from` by gzip to `to`, and remove `from`.
+fn compress(from: &Path, to: &Path) -> std::io::Result<()> {
+    let mut encoder = GzEncoder::new(File::create(to)?, Compression::default());
+
+    std::io::copy(&mut File::open(from)?, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    std::fs::remove_file(from)
+}
@@ -91,17 +91,15 @@ impl<'a> Splitted<'a> {
                         state = State::Normal;
                         beg = pos;
                     }
-                    State::Normal if char.is_whitespace() => {
-                        if spec.last().is_none() {
-                            if pos - beg > 0 {
-                                words.push(Slice {
-                                    beg,
-                                    dat: &line[beg..pos],
-                                });
-                            }
-                            state = State::Space;
-                            beg = pos;
+                    State::Normal if char.is_whitespace() && spec.last().is_none() => {
+                        if pos - beg > 0 {
+                            words.push(Slice {
+                                beg,
+                                dat: &line[beg..pos],
+                            });
                         }
+                        state = State::Space;
+                        beg = pos;
                     }
                     State::Normal => {}
                     _ => {}
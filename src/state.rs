@@ -0,0 +1,224 @@
+use std::fs::File;
+use std::fs::Permissions;
+use std::os::unix::fs::DirBuilderExt;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use nix::fcntl::Flock;
+use nix::fcntl::FlockArg;
+use nix::unistd::Uid;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+
+use crate::config::RestartConfig;
+use crate::manager::SsInstance;
+use crate::procfs::Listen;
+use crate::supervisor::Proc;
+use crate::supervisor::ProcSpec;
+use crate::supervisor::Supervisor;
+
+/// Record of a process in the state file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcRecord {
+    pub pid: Option<u32>,
+
+    pub spec: ProcSpec,
+
+    /// Ports expected to be bound by the process
+    #[serde(default)]
+    pub ports: Vec<Listen>,
+}
+
+/// Record of an instance in the state file, one file per instance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceRecord {
+    pub id: usize,
+
+    /// Index of the deploy configuration
+    pub index: usize,
+
+    pub started: SystemTime,
+
+    /// Generated configurations of ssserver and kcptun, removed when the instance is stopped
+    #[serde(default)]
+    pub configs: Vec<PathBuf>,
+
+    pub restart: RestartConfig,
+
+    pub ss: ProcRecord,
+
+    pub kcp: Option<ProcRecord>,
+}
+
+pub fn default_state_dir() -> PathBuf {
+    let dir = match std::env::var_os("XDG_STATE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(shellexpand::tilde("~/.local/state").as_ref()),
+    };
+
+    dir.join("rssdeploy")
+}
+
+/// Directory of the generated configurations, they contain the secrets.
+pub fn runtime_dir() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("rssdeploy"),
+        None => std::env::temp_dir().join(format!("rssdeploy-{}", whoami::username())),
+    }
+}
+
+/// Create the directory only accessible by current user,
+/// refuse to use it if it is owned by others.
+pub fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)?;
+
+    let meta = std::fs::symlink_metadata(dir)?;
+
+    if !meta.is_dir() || meta.uid() != Uid::current().as_raw() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!(
+                "`{}` is not a directory owned by current user",
+                dir.display()
+            ),
+        ));
+    }
+    if meta.mode() & 0o077 != 0 {
+        std::fs::set_permissions(dir, Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+/// Write the file only readable by current user, it contains the secrets.
+pub async fn write_private(path: &Path, content: String) -> std::io::Result<()> {
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)
+        .await?;
+
+    // the mode is not changed if the file exists
+    file.set_permissions(Permissions::from_mode(0o600)).await?;
+    file.write_all(content.as_bytes()).await
+}
+
+/// Remove the generated configurations of instance.
+pub fn remove_configs(configs: &[PathBuf]) {
+    for path in configs.iter() {
+        if let Err(e) = std::fs::remove_file(path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                eprintln!(
+                    "WARN! Failed remove configuration `{}`: {e:?}",
+                    path.display()
+                );
+            }
+        }
+    }
+}
+
+pub fn record_path(dir: &Path, id: usize) -> PathBuf {
+    dir.join(format!("instance_{id}.json"))
+}
+
+pub fn load_next_id(dir: &Path) -> usize {
+    std::fs::read_to_string(dir.join("next_id"))
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or_default()
+}
+
+pub fn save_next_id(dir: &Path, id: usize) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    write_atomic(&dir.join("next_id"), id.to_string())
+}
+
+pub fn save_record(path: &Path, record: &InstanceRecord) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    write_atomic(path, serde_json::to_string_pretty(record)?)
+}
+
+/// Write the content to a temporary file and rename it to `path`,
+/// the file is not corrupted if rssdeploy crashed in the middle.
+fn write_atomic(path: &Path, content: String) -> std::io::Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+
+    temp.push(".tmp");
+    std::fs::write(&temp, content)?;
+    std::fs::rename(&temp, path)
+}
+
+/// Lock the state directory exclusively until the returned lock dropped,
+/// the rssdeploy running at same time waits for it.
+pub fn lock(dir: &Path) -> std::io::Result<Flock<File>> {
+    std::fs::create_dir_all(dir)?;
+
+    let file = File::options()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(dir.join("lock"))?;
+
+    Flock::lock(file, FlockArg::LockExclusive).map_err(|(_, e)| e.into())
+}
+
+/// Adopt the instances recorded in state directory, the process is adopted
+/// if it is still alive and its command line matches the record.
+pub fn adopt(dir: &Path) -> color_eyre::Result<Vec<SsInstance>> {
+    let mut insts = vec![];
+
+    if !dir.is_dir() {
+        return Ok(insts);
+    }
+    let _lock = lock(dir)?;
+
+    for entry in std::fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+
+        if path.extension().and_then(|v| v.to_str()) != Some("json") {
+            continue;
+        }
+
+        let record: InstanceRecord = match std::fs::read_to_string(&path)
+            .map_err(color_eyre::Report::from)
+            .and_then(|v| Ok(serde_json::from_str(&v)?))
+        {
+            Ok(record) => record,
+            Err(e) => {
+                eprintln!("WARN! Failed load state file `{}`: {e:?}", path.display());
+                continue;
+            }
+        };
+        let matched = |rec: &ProcRecord| {
+            rec.pid
+                .filter(|pid| rec.spec.is_running(*pid))
+                .map(Proc::Adopted)
+        };
+        let ss = matched(&record.ss);
+        let kcp = record.kcp.as_ref().and_then(matched);
+
+        if ss.is_none() && kcp.is_none() {
+            remove_configs(&record.configs);
+            std::fs::remove_file(&path)?;
+            continue;
+        }
+        insts.push(SsInstance {
+            id: record.id,
+            index: record.index,
+            supervisor: Supervisor::new(record, ss, kcp, Some(path)),
+        });
+    }
+    insts.sort_by_key(|v| v.id);
+
+    Ok(insts)
+}
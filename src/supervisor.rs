@@ -0,0 +1,657 @@
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use nix::sys::signal::kill;
+use nix::sys::signal::Signal;
+use nix::unistd::Pid;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::process::Child;
+use tokio::process::Command;
+use tokio::select;
+use tokio::spawn;
+use tokio::sync::mpsc::channel;
+use tokio::sync::mpsc::Receiver;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+use tokio::time::sleep_until;
+
+use crate::capture::pump;
+use crate::capture::Capture;
+use crate::capture::Sink;
+use crate::config::LogConfig;
+use crate::config::RestartConfig;
+use crate::config::RestartPolicy;
+use crate::config::REDACTED;
+use crate::procfs::Listen;
+use crate::rotate::RotatingFile;
+use crate::state::remove_configs;
+use crate::state::save_record;
+use crate::state::InstanceRecord;
+use crate::state::ProcRecord;
+
+/// Everything needed to (re)spawn a child process.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcSpec {
+    pub bin: PathBuf,
+
+    pub args: Vec<String>,
+
+    pub stdout: Option<PathBuf>,
+
+    pub stderr: Option<PathBuf>,
+
+    #[serde(default)]
+    pub log: LogConfig,
+
+    /// Output kept in memory, it is lost when rssdeploy exits
+    #[serde(skip)]
+    pub capture: Option<Capture>,
+}
+
+impl ProcSpec {
+    pub fn new(bin: impl Into<PathBuf>) -> Self {
+        Self {
+            bin: bin.into(),
+            args: vec![],
+            stdout: None,
+            stderr: None,
+            log: LogConfig::default(),
+            capture: None,
+        }
+    }
+
+    pub fn arg(&mut self, arg: impl AsRef<OsStr>) -> &mut Self {
+        self.args.push(arg.as_ref().to_string_lossy().into_owned());
+        self
+    }
+
+    /// Check if the `/proc/<pid>/cmdline` of process is started by current spec,
+    /// the bin may be run by an interpreter.
+    pub fn is_running(&self, pid: u32) -> bool {
+        let Ok(cmdline) = std::fs::read(format!("/proc/{pid}/cmdline")) else {
+            return false;
+        };
+        let mut cmdline: Vec<_> = cmdline
+            .split(|v| *v == 0)
+            .map(|v| OsStr::from_bytes(v).to_string_lossy().into_owned())
+            .collect();
+
+        // cmdline is end with '\0'
+        if cmdline.last().is_some_and(|v| v.is_empty()) {
+            cmdline.pop();
+        }
+        cmdline.len() > self.args.len()
+            && cmdline.ends_with(&self.args)
+            && cmdline[..cmdline.len() - self.args.len()]
+                .iter()
+                .any(|v| Path::new(v).file_name() == self.bin.file_name())
+    }
+
+    /// Spawn the process, the log files are truncated if `truncate` is true,
+    /// otherwise new output is appended to them.
+    ///
+    /// The output is copied through pipes to [`RotatingFile`] if the rotation is enabled,
+    /// or to the [`Capture`] if the log path is not set.
+    pub fn spawn(&self, truncate: bool) -> std::io::Result<Child> {
+        let mut cmd = Command::new(&self.bin);
+        let mut out_sink = None;
+        let mut err_sink = None;
+
+        cmd.args(&self.args);
+        // don't receive the ctrl-c of terminal
+        cmd.process_group(0);
+        if self.log.rotating() {
+            (out_sink, err_sink) = self.open(
+                |path| {
+                    let file = RotatingFile::open(path, &self.log, truncate)?;
+
+                    Ok(Sink::File(Arc::new(Mutex::new(file))))
+                },
+                |sink| Ok(sink.clone()),
+            )?;
+        } else {
+            let (out_log, err_log) = self.open(
+                |path| {
+                    std::fs::File::options()
+                        .write(true)
+                        .append(!truncate)
+                        .truncate(truncate)
+                        .create(true)
+                        .open(path)
+                },
+                std::fs::File::try_clone,
+            )?;
+
+            if let Some(out_log) = out_log {
+                cmd.stdout(out_log);
+            }
+            if let Some(err_log) = err_log {
+                cmd.stderr(err_log);
+            }
+        }
+        if let Some(capture) = &self.capture {
+            if self.stdout.is_none() {
+                out_sink = Some(Sink::Ring(capture.out.clone()));
+            }
+            if self.stderr.is_none() {
+                err_sink = Some(Sink::Ring(capture.err.clone()));
+            }
+        }
+        if out_sink.is_some() {
+            cmd.stdout(Stdio::piped());
+        }
+        if err_sink.is_some() {
+            cmd.stderr(Stdio::piped());
+        }
+
+        let mut child = cmd.spawn()?;
+        let pump_all = || {
+            if let (Some(pipe), Some(sink)) = (child.stdout.take(), out_sink) {
+                pump(pipe.into_owned_fd()?, sink)?;
+            }
+            if let (Some(pipe), Some(sink)) = (child.stderr.take(), err_sink) {
+                pump(pipe.into_owned_fd()?, sink)?;
+            }
+            std::io::Result::Ok(())
+        };
+
+        if let Err(e) = pump_all() {
+            let _ = child.start_kill();
+            return Err(e);
+        }
+        Ok(child)
+    }
+
+    /// Open the files of stdout and stderr, the file of stdout is shared if they are same,
+    /// the outputs would overwrite each other if it is opened twice.
+    fn open<T>(
+        &self,
+        open: impl Fn(&PathBuf) -> std::io::Result<T>,
+        share: impl Fn(&T) -> std::io::Result<T>,
+    ) -> std::io::Result<(Option<T>, Option<T>)> {
+        let out_log = self.stdout.as_ref().map(&open).transpose()?;
+        let err_log = match (&out_log, &self.stderr) {
+            (Some(file), Some(path)) if self.stdout.as_ref() == Some(path) => Some(share(file)?),
+            (_, Some(path)) => Some(open(path)?),
+            _ => None,
+        };
+
+        Ok((out_log, err_log))
+    }
+}
+
+/// The options followed by a secret, such as `-key` of kcptun.
+const SECRET_ARGS: &[&str] = &["-k", "-key", "--key", "--password"];
+
+/// Display the command line, the secrets are replaced by [`REDACTED`].
+impl std::fmt::Display for ProcSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.bin.display())?;
+        for (i, arg) in self.args.iter().enumerate() {
+            let secret = i > 0 && SECRET_ARGS.contains(&self.args[i - 1].as_str());
+
+            write!(f, " {}", if secret { REDACTED } else { arg })?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcState {
+    /// Pid of the running process, [`None`] if it exited
+    pub pid: Option<u32>,
+
+    pub started: SystemTime,
+
+    /// Status and time of the last exit
+    pub exited: Option<Exited>,
+
+    pub restarts: u32,
+
+    /// Ports expected to be bound by the process
+    pub ports: Vec<Listen>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Exited {
+    /// Exit status, unknown for the adopted process
+    pub status: Option<ExitStatus>,
+
+    pub at: SystemTime,
+}
+
+impl Exited {
+    fn now(status: Option<ExitStatus>) -> Self {
+        Self {
+            status,
+            at: SystemTime::now(),
+        }
+    }
+}
+
+impl ProcState {
+    /// Time since the process started.
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed().unwrap_or_default()
+    }
+
+    fn new(pid: Option<u32>, ports: Vec<Listen>) -> Self {
+        Self {
+            pid,
+            started: SystemTime::now(),
+            exited: None,
+            restarts: 0,
+            ports,
+        }
+    }
+}
+
+/// How a process was stopped.
+#[derive(Debug, Clone, Copy)]
+pub struct Stopped {
+    /// Pid of the process, [`None`] if it already exited
+    pub pid: Option<u32>,
+
+    pub status: Option<ExitStatus>,
+
+    /// Killed by SIGKILL after the grace period
+    pub killed: bool,
+}
+
+impl std::fmt::Display for Stopped {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.pid, self.status) {
+            (Some(pid), Some(status)) => write!(f, "pid {pid} stopped, {status}")?,
+            (Some(pid), None) => write!(f, "pid {pid} stopped, unknown status")?,
+            (None, Some(status)) => write!(f, "already exited, {status}")?,
+            (None, None) => write!(f, "already exited")?,
+        }
+        if self.killed {
+            write!(f, " (killed after timeout)")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct StopReport {
+    pub ss: Stopped,
+
+    pub kcp: Option<Stopped>,
+}
+
+#[derive(Debug)]
+enum Control {
+    Stop(Duration, oneshot::Sender<StopReport>),
+
+    /// The configuration is moved to the index
+    Index(usize, oneshot::Sender<()>),
+}
+
+enum Event {
+    Exit(Option<ExitStatus>),
+    Retry,
+}
+
+/// The process is either spawned by us or adopted from state file.
+#[derive(Debug)]
+pub enum Proc {
+    Child(Child),
+
+    Adopted(u32),
+}
+
+impl Proc {
+    fn id(&self) -> Option<u32> {
+        match self {
+            Proc::Child(child) => child.id(),
+            Proc::Adopted(pid) => Some(*pid),
+        }
+    }
+
+    async fn wait(&mut self) -> Option<ExitStatus> {
+        match self {
+            Proc::Child(child) => child.wait().await.ok(),
+            Proc::Adopted(pid) => {
+                while is_alive(*pid) {
+                    sleep(Duration::from_millis(500)).await;
+                }
+                None
+            }
+        }
+    }
+
+    async fn kill(&mut self) -> Option<ExitStatus> {
+        match self {
+            Proc::Child(child) => match child.kill().await {
+                Ok(()) => child.wait().await.ok(),
+                Err(_) => None,
+            },
+            Proc::Adopted(pid) => {
+                let _ = kill(Pid::from_raw(*pid as i32), Signal::SIGKILL);
+                None
+            }
+        }
+    }
+}
+
+/// The adopted process is not reaped by us, so check the zombie state too.
+fn is_alive(pid: u32) -> bool {
+    let zombie = std::fs::read_to_string(format!("/proc/{pid}/stat"))
+        .ok()
+        .and_then(|v| {
+            v.rsplit_once(')')
+                .map(|(_, v)| v.trim_start().starts_with('Z'))
+        })
+        .unwrap_or_default();
+
+    !zombie && kill(Pid::from_raw(pid as i32), None).is_ok()
+}
+
+struct Watched {
+    spec: ProcSpec,
+
+    proc: Option<Proc>,
+
+    state: Arc<Mutex<ProcState>>,
+
+    /// Consecutive restart attempts
+    attempts: u32,
+
+    spawned: Instant,
+
+    retry_at: Option<Instant>,
+}
+
+impl Watched {
+    fn new(record: &ProcRecord, proc: Option<Proc>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ProcState::new(
+                proc.as_ref().and_then(|v| v.id()),
+                record.ports.clone(),
+            ))),
+            spec: record.spec.clone(),
+            proc,
+            attempts: 0,
+            spawned: Instant::now(),
+            retry_at: None,
+        }
+    }
+
+    async fn event(&mut self) -> Event {
+        if let Some(proc) = self.proc.as_mut() {
+            Event::Exit(proc.wait().await)
+        } else if let Some(retry_at) = self.retry_at {
+            sleep_until(retry_at.into()).await;
+            Event::Retry
+        } else {
+            std::future::pending().await
+        }
+    }
+
+    fn on_exit(&mut self, status: Option<ExitStatus>, restart: &RestartConfig) {
+        let now = Instant::now();
+
+        self.proc = None;
+        {
+            let mut state = self.state.lock().unwrap();
+
+            state.pid = None;
+            state.exited = Some(Exited::now(status));
+        }
+        // a process running longer than the maximum backoff is considered healthy
+        if now.duration_since(self.spawned) > Duration::from_millis(restart.max_backoff) {
+            self.attempts = 0;
+        }
+
+        let need_restart = match restart.policy {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnFailure => !status.map(|v| v.success()).unwrap_or_default(),
+            RestartPolicy::Always => true,
+        };
+
+        if need_restart && self.attempts < restart.max_retries {
+            self.retry_at = Some(now + restart.delay(self.attempts));
+            self.attempts += 1;
+        }
+    }
+
+    fn on_retry(&mut self, restart: &RestartConfig) {
+        self.retry_at = None;
+        self.spawned = Instant::now();
+        match self.spec.spawn(false) {
+            Ok(child) => {
+                let mut state = self.state.lock().unwrap();
+
+                state.pid = child.id();
+                state.started = SystemTime::now();
+                state.restarts += 1;
+                self.proc = Some(Proc::Child(child));
+            }
+            Err(_) => {
+                self.on_exit(None, restart);
+            }
+        }
+    }
+
+    fn handle(&mut self, event: Event, restart: &RestartConfig) {
+        match event {
+            Event::Exit(status) => self.on_exit(status, restart),
+            Event::Retry => self.on_retry(restart),
+        }
+    }
+
+    /// Send SIGTERM to the process, and SIGKILL if it not exit in `timeout`.
+    async fn stop(&mut self, timeout: Duration) -> Stopped {
+        self.retry_at = None;
+
+        let Some(mut proc) = self.proc.take() else {
+            let state = self.state.lock().unwrap();
+
+            return Stopped {
+                pid: None,
+                status: state.exited.and_then(|v| v.status),
+                killed: false,
+            };
+        };
+        let pid = proc.id();
+
+        if let Some(pid) = pid {
+            let _ = kill(Pid::from_raw(pid as i32), Signal::SIGTERM);
+        }
+
+        let (status, killed) = match tokio::time::timeout(timeout, proc.wait()).await {
+            Ok(status) => (status, false),
+            Err(_) => (proc.kill().await, true),
+        };
+        let mut state = self.state.lock().unwrap();
+
+        state.pid = None;
+        state.exited = Some(Exited::now(status));
+        Stopped {
+            pid,
+            status,
+            killed,
+        }
+    }
+}
+
+async fn opt_event(watched: &mut Option<Watched>) -> Event {
+    match watched {
+        Some(watched) => watched.event().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Watch the ssserver and kcptun process of an instance, restart them
+/// according to the [`RestartConfig`].
+#[derive(Debug)]
+pub struct Supervisor {
+    ss: Arc<Mutex<ProcState>>,
+
+    kcp: Option<Arc<Mutex<ProcState>>>,
+
+    ss_spec: ProcSpec,
+
+    kcp_spec: Option<ProcSpec>,
+
+    ctrl: Sender<Control>,
+
+    task: JoinHandle<()>,
+}
+
+impl Supervisor {
+    /// Supervise the processes of `record`, and keep the state file at `store` update.
+    pub fn new(
+        record: InstanceRecord,
+        ss: Option<Proc>,
+        kcp: Option<Proc>,
+        store: Option<PathBuf>,
+    ) -> Self {
+        let (ctrl, ctrl_rx) = channel(1);
+        let restart = &record.restart;
+        let mut ss = Watched::new(&record.ss, ss);
+        let mut kcp = record.kcp.as_ref().map(|v| Watched::new(v, kcp));
+
+        for watched in std::iter::once(&mut ss).chain(kcp.as_mut()) {
+            if matches!(watched.proc, Some(Proc::Adopted(_))) {
+                watched.state.lock().unwrap().started = record.started;
+            }
+        }
+        // the process is already exited when adopting
+        if ss.proc.is_none() {
+            ss.on_exit(None, restart);
+        }
+        if let Some(kcp) = kcp.as_mut().filter(|v| v.proc.is_none()) {
+            kcp.on_exit(None, restart);
+        }
+
+        let mut record = record;
+
+        // the state file must be written before return, the caller may exit
+        Self::save(&mut record, store.as_ref(), &ss, kcp.as_ref());
+        Self {
+            ss: ss.state.clone(),
+            kcp: kcp.as_ref().map(|v| v.state.clone()),
+            ss_spec: ss.spec.clone(),
+            kcp_spec: kcp.as_ref().map(|v| v.spec.clone()),
+            ctrl,
+            task: spawn(Self::supervise(record, store, ss, kcp, ctrl_rx)),
+        }
+    }
+
+    pub fn ss(&self) -> ProcState {
+        self.ss.lock().unwrap().clone()
+    }
+
+    pub fn kcp(&self) -> Option<ProcState> {
+        self.kcp.as_ref().map(|v| v.lock().unwrap().clone())
+    }
+
+    pub fn ss_spec(&self) -> &ProcSpec {
+        &self.ss_spec
+    }
+
+    pub fn kcp_spec(&self) -> Option<&ProcSpec> {
+        self.kcp_spec.as_ref()
+    }
+
+    /// Stop the supervisor and terminate the processes,
+    /// the processes are killed if they are not exit in `timeout`.
+    pub async fn stop(&mut self, timeout: Duration) -> color_eyre::Result<StopReport> {
+        let (tx, rx) = oneshot::channel();
+
+        self.ctrl.send(Control::Stop(timeout, tx)).await?;
+        let report = rx.await?;
+
+        (&mut self.task).await?;
+        Ok(report)
+    }
+
+    /// Update the index of configuration in the state file.
+    pub async fn set_index(&self, index: usize) -> color_eyre::Result<()> {
+        let (tx, rx) = oneshot::channel();
+
+        self.ctrl.send(Control::Index(index, tx)).await?;
+        Ok(rx.await?)
+    }
+
+    fn save(
+        record: &mut InstanceRecord,
+        store: Option<&PathBuf>,
+        ss: &Watched,
+        kcp: Option<&Watched>,
+    ) {
+        record.ss.pid = ss.state.lock().unwrap().pid;
+        if let (Some(rec), Some(kcp)) = (record.kcp.as_mut(), kcp) {
+            rec.pid = kcp.state.lock().unwrap().pid;
+        }
+        if let Some(path) = store {
+            if let Err(e) = save_record(path, record) {
+                eprintln!("WARN! Failed save state file `{}`: {e:?}", path.display());
+            }
+        }
+    }
+
+    async fn supervise(
+        mut record: InstanceRecord,
+        store: Option<PathBuf>,
+        mut ss: Watched,
+        mut kcp: Option<Watched>,
+        mut ctrl: Receiver<Control>,
+    ) {
+        let restart = record.restart.clone();
+
+        loop {
+            select! {
+                msg = ctrl.recv() => {
+                    let (timeout, reply) = match msg {
+                        Some(Control::Stop(timeout, reply)) => (timeout, reply),
+                        Some(Control::Index(index, reply)) => {
+                            record.index = index;
+                            Self::save(&mut record, store.as_ref(), &ss, kcp.as_ref());
+                            let _ = reply.send(());
+                            continue;
+                        }
+                        // the supervisor is dropped, leave the processes running
+                        None => break,
+                    };
+                    let (ss, kcp) = tokio::join!(ss.stop(timeout), async {
+                        match kcp.as_mut() {
+                            Some(kcp) => Some(kcp.stop(timeout).await),
+                            None => None,
+                        }
+                    });
+
+                    remove_configs(&record.configs);
+                    if let Some(path) = store.as_ref() {
+                        let _ = std::fs::remove_file(path);
+                    }
+                    let _ = reply.send(StopReport { ss, kcp });
+                    break;
+                }
+                event = ss.event() => {
+                    ss.handle(event, &restart);
+                }
+                event = opt_event(&mut kcp) => {
+                    if let Some(kcp) = kcp.as_mut() {
+                        kcp.handle(event, &restart);
+                    }
+                }
+            }
+            Self::save(&mut record, store.as_ref(), &ss, kcp.as_ref());
+        }
+    }
+}
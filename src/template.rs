@@ -0,0 +1,107 @@
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use color_eyre::eyre::eyre;
+
+use crate::config::Method;
+
+/// Names of the variables can be used in `bin`, `kcp` and log paths.
+pub const VARS: &[&str] = &["index", "instance_id", "port", "kcp_port", "method", "date"];
+
+/// Values of the template variables, such as `{index}` and `{port}`.
+#[derive(Debug, Clone, Default)]
+pub struct Vars {
+    /// Index of the deploy configuration
+    pub index: usize,
+
+    pub instance_id: usize,
+
+    /// Listen port of ssserver
+    pub port: u32,
+
+    /// Listen port of kcptun
+    pub kcp_port: u32,
+
+    pub method: Method,
+}
+
+impl Vars {
+    pub fn value(&self, name: &str) -> Option<String> {
+        Some(match name {
+            "index" => self.index.to_string(),
+            "instance_id" => self.instance_id.to_string(),
+            "port" => self.port.to_string(),
+            "kcp_port" => self.kcp_port.to_string(),
+            "method" => self.method.to_string(),
+            "date" => today(),
+            _ => return None,
+        })
+    }
+
+    /// Replace the variables, the `${NAME}` is left to [`shellexpand`].
+    pub fn replace(&self, val: &str) -> color_eyre::Result<String> {
+        let mut ret = String::with_capacity(val.len());
+        let mut rest = val;
+
+        while let Some(start) = rest.find('{') {
+            let (head, tail) = rest.split_at(start);
+            let Some(end) = tail.find('}') else {
+                break;
+            };
+
+            ret.push_str(head);
+            if head.ends_with('$') {
+                ret.push_str(&tail[..=end]);
+            } else {
+                let name = &tail[1..end];
+
+                ret.push_str(&self.value(name).ok_or_else(|| {
+                    eyre!(
+                        "Unknown variable `{{{name}}}`, available are: {}",
+                        VARS.join(", ")
+                    )
+                })?);
+            }
+            rest = &tail[end + 1..];
+        }
+        ret.push_str(rest);
+        Ok(ret)
+    }
+
+    /// Replace the variables and expand the path like shell.
+    pub fn expand(&self, path: &Path) -> color_eyre::Result<PathBuf> {
+        let expanded = match path.to_str() {
+            Some(val) => self.replace(val).map(PathBuf::from),
+            None => Ok(path.to_path_buf()),
+        };
+        let expanded = expanded.and_then(|v| Ok(shellexpand::path::full(&v)?.into_owned()));
+
+        expanded.map_err(|e| eyre!("Can not expand `{}`: {e}", path.display()))
+    }
+}
+
+/// The current date in UTC, such as `2025-07-01`.
+pub fn today() -> String {
+    date(SystemTime::now())
+}
+
+/// The date of `time` in UTC.
+pub fn date(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    // convert the days since epoch to civil date
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
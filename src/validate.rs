@@ -0,0 +1,258 @@
+use std::fmt::Display;
+use std::path::Path;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use nix::unistd::access;
+use nix::unistd::AccessFlags;
+use serde::Serialize;
+
+use crate::config::default_kcp_port;
+use crate::config::DeployConfig;
+use crate::config::Method;
+use crate::config::Secret;
+use crate::config::SecretRef;
+use crate::template::Vars;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Error,
+
+    Warning,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Level::Error => write!(f, "error"),
+            Level::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found in the configurations.
+#[derive(Debug, Clone, Serialize)]
+pub struct Issue {
+    pub level: Level,
+
+    /// JSON path of the value, such as `$[0].ss_cfg.server_port`
+    pub path: String,
+
+    pub message: String,
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}: {}", self.level, self.path, self.message)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Issues(Vec<Issue>);
+
+impl Issues {
+    fn error(&mut self, index: usize, path: &str, message: impl Into<String>) {
+        self.push(Level::Error, index, path, message.into());
+    }
+
+    fn warning(&mut self, index: usize, path: &str, message: impl Into<String>) {
+        self.push(Level::Warning, index, path, message.into());
+    }
+
+    fn push(&mut self, level: Level, index: usize, path: &str, message: String) {
+        self.0.push(Issue {
+            level,
+            path: format!("$[{index}].{path}"),
+            message,
+        });
+    }
+}
+
+/// Check the configurations, return the errors and warnings.
+pub fn validate(cfgs: &[DeployConfig]) -> Vec<Issue> {
+    let mut issues = Issues::default();
+
+    for (index, cfg) in cfgs.iter().enumerate() {
+        check_config(&mut issues, index, cfg);
+
+        let port = cfg.ss_cfg.server_port;
+
+        if let Some(other) = cfgs[..index]
+            .iter()
+            .position(|v| v.ss_cfg.server_port == port)
+        {
+            issues.warning(
+                index,
+                "ss_cfg.server_port",
+                format!("port {port} is also used by configuration {other}"),
+            );
+        }
+    }
+    issues.0
+}
+
+fn check_config(issues: &mut Issues, index: usize, cfg: &DeployConfig) {
+    let ss_cfg = &cfg.ss_cfg;
+    let port = ss_cfg.server_port;
+
+    if port == 0 || port > u16::MAX as u32 {
+        issues.error(index, "ss_cfg.server_port", format!("invalid port {port}"));
+    }
+    // the instance id is unknown before start
+    let vars = Vars {
+        index,
+        instance_id: 0,
+        port,
+        kcp_port: default_kcp_port(port).unwrap_or_default(),
+        method: ss_cfg.method,
+    };
+
+    match &ss_cfg.password {
+        Secret::Plain(password) => check_password(issues, index, ss_cfg.method, password),
+        Secret::Ref(secret) => check_secret(issues, index, "ss_cfg.password", secret),
+    }
+    check_executable(issues, index, "bin", &vars, &cfg.bin, Level::Error);
+    for (path, log) in [
+        ("out_log", &cfg.out_log),
+        ("err_log", &cfg.err_log),
+        ("kcp_log", &cfg.kcp_log),
+    ] {
+        if let Some(log) = log {
+            check_log(issues, index, path, &vars, log);
+        }
+    }
+    if let Some(kcp_cfg) = &cfg.kcp_cfg {
+        // kcptun listen at `server_port + 1` by default
+        if port == u16::MAX as u32 {
+            issues.error(
+                index,
+                "ss_cfg.server_port",
+                format!("kcptun port {} overflows", u64::from(port) + 1),
+            );
+        }
+        match &kcp_cfg.key {
+            Secret::Plain(key) if key.is_empty() => {
+                issues.warning(index, "kcp_cfg.key", "empty key of kcptun")
+            }
+            Secret::Plain(_) => {}
+            Secret::Ref(secret) => check_secret(issues, index, "kcp_cfg.key", secret),
+        }
+        // kcptun is optional when start
+        check_executable(issues, index, "kcp", &vars, &cfg.kcp, Level::Warning);
+    }
+}
+
+fn check_password(issues: &mut Issues, index: usize, method: Method, password: &str) {
+    let path = "ss_cfg.password";
+
+    if let Some(len) = method.key_len() {
+        // the multiple keys are separated by ':'
+        for key in password.split(':') {
+            match STANDARD.decode(key) {
+                Ok(key) if key.len() == len => {}
+                Ok(key) => issues.error(
+                    index,
+                    path,
+                    format!("{method} needs a {len} bytes key, got {} bytes", key.len()),
+                ),
+                Err(_) => issues.error(
+                    index,
+                    path,
+                    format!("{method} needs a base64 encoded key, use `genkey` to generate one"),
+                ),
+            }
+        }
+    } else if password.is_empty() && !matches!(method, Method::Plain | Method::None) {
+        issues.error(index, path, "empty password");
+    }
+}
+
+/// The references are resolved when start, only check they are available.
+fn check_secret(issues: &mut Issues, index: usize, path: &str, secret: &SecretRef) {
+    match secret {
+        SecretRef::Env(name) if std::env::var_os(name).is_none() => issues.warning(
+            index,
+            path,
+            format!("environment variable `{name}` is not set"),
+        ),
+        SecretRef::File(file) => match shellexpand::path::full(file) {
+            Ok(file) if !file.is_file() => {
+                issues.error(index, path, format!("`{}` not found", file.display()))
+            }
+            Ok(_) => {}
+            Err(e) => issues.error(
+                index,
+                path,
+                format!("can not expand `{}`: {e}", file.display()),
+            ),
+        },
+        _ => {}
+    }
+}
+
+fn check_executable(
+    issues: &mut Issues,
+    index: usize,
+    path: &str,
+    vars: &Vars,
+    bin: &Path,
+    level: Level,
+) {
+    let message = match vars.expand(bin) {
+        Ok(bin) if !bin.is_file() => format!("`{}` not found", bin.display()),
+        Ok(bin) if access(bin.as_path(), AccessFlags::X_OK).is_err() => {
+            format!("`{}` is not executable", bin.display())
+        }
+        Ok(_) => return,
+        Err(e) => e.to_string(),
+    };
+
+    issues.push(level, index, path, message);
+}
+
+fn check_log(issues: &mut Issues, index: usize, path: &str, vars: &Vars, log: &Path) {
+    let log = match vars.expand(log) {
+        Ok(log) => log,
+        Err(e) => return issues.error(index, path, e.to_string()),
+    };
+
+    if log.exists() {
+        if access(log.as_path(), AccessFlags::W_OK).is_err() {
+            issues.error(index, path, format!("`{}` is not writable", log.display()));
+        }
+    } else if let Some(dir) = log.ancestors().skip(1).find(|v| v.exists()) {
+        // the directories are created when start
+        if !dir.is_dir() || access(dir, AccessFlags::W_OK | AccessFlags::X_OK).is_err() {
+            issues.error(
+                index,
+                path,
+                format!("can not create `{}` in `{}`", log.display(), dir.display()),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::ConfigFile;
+    use crate::config::FileFormat;
+
+    use super::*;
+
+    #[test]
+    fn sample_passes_check() {
+        let value = FileFormat::Json
+            .parse(include_str!("../shadowsocks.json"))
+            .unwrap();
+        let cfgs = ConfigFile::from_value(value).unwrap().resolve().unwrap();
+        // the executables depend on the installation
+        let errors: Vec<_> = validate(&cfgs)
+            .into_iter()
+            .filter(|v| v.level == Level::Error && !v.path.ends_with(".bin"))
+            .collect();
+
+        assert_eq!(cfgs.len(), 2);
+        assert!(errors.is_empty(), "{errors:?}");
+    }
+}